@@ -1,7 +1,7 @@
 #[macro_use]
 extern crate timeit;
 
-use syslog_rfc5424::parse_message;
+use syslog_rfc5424::{parse_message, parse_message_ref};
 
 // Stupid benchmark tool using the timeit! macro because the official benchmarking tools are
 // **still* nightly-Rust-only, even though they're, like, a year old
@@ -22,6 +22,22 @@ fn bench_serde() {
     });
 }
 
+#[cfg(feature = "interning")]
+fn bench_interning() {
+    use syslog_rfc5424::{ParseOptions, Parser};
+
+    println!("Parsing a message with repetitive structured data through a Parser:");
+    let sd_message = r#"<190>1 2016-02-21T01:19:11+00:00 batch6sj - - - [meta sequenceId="21881798" x-group="37051387"][origin x-service="tracking" software="rsyslogd" swVersion="8.24.0"][timeQuality tzKnown="1" isSynced="1" syncAccuracy="60000"] metascutellar conversationalist"#;
+    let mut parser = Parser::new(ParseOptions::default(), 1024);
+    timeit!({
+        parser.parse(sd_message).unwrap();
+    });
+    println!("... and the same message without one:");
+    timeit!({
+        parse_message(sd_message).unwrap();
+    });
+}
+
 fn main() {
     println!("Parsing the smallest possible message:");
     let simple_message = "<1>1 - - - - - -";
@@ -33,6 +49,11 @@ fn main() {
     timeit!({
         parse_message(complicated_message).unwrap();
     });
+    println!("Parsing a message with a high-precision offset timestamp:");
+    let timestamp_message = "<14>1 2017-07-26T14:47:35.869952+05:30 - - - - - -";
+    timeit!({
+        parse_message(timestamp_message).unwrap();
+    });
     println!("Parsing a very long message:");
     let large_message = r#"<190>1 2016-02-21T01:19:11+00:00 batch6sj - - - [meta sequenceId="21881798" x-group="37051387"][origin x-service="tracking"] metascutellar conversationalist nephralgic exogenetic graphy streng outtaken acouasm amateurism prenotice Lyonese bedull antigrammatical diosphenol gastriloquial bayoneteer sweetener naggy roughhouser dighter addend sulphacid uneffectless ferroprussiate reveal Mazdaist plaudite Australasian distributival wiseman rumness Seidel topazine shahdom sinsion mesmerically pinguedinous ophthalmotonometer scuppler wound eciliate expectedly carriwitchet dictatorialism bindweb pyelitic idic atule kokoon poultryproof rusticial seedlip nitrosate splenadenoma holobenthic uneternal Phocaean epigenic doubtlessly indirection torticollar robomb adoptedly outspeak wappenschawing talalgia Goop domitic savola unstrafed carded unmagnified mythologically orchester obliteration imperialine undisobeyed galvanoplastical cycloplegia quinquennia foremean umbonal marcgraviaceous happenstance theoretical necropoles wayworn Igbira pseudoangelic raising unfrounced lamasary centaurial Japanolatry microlepidoptera"#;
     timeit!({
@@ -43,6 +64,16 @@ fn main() {
     timeit!({
         parse_message(average_message).unwrap();
     });
+    println!("Parsing an average message without copying:");
+    timeit!({
+        parse_message_ref(average_message).unwrap();
+    });
+    println!("Parsing a very long message without copying:");
+    timeit!({
+        parse_message_ref(large_message).unwrap();
+    });
     #[cfg(feature = "serde-serialize")]
     bench_serde();
+    #[cfg(feature = "interning")]
+    bench_interning();
 }
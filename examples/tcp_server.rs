@@ -0,0 +1,70 @@
+use std::env;
+use std::io::{self, Read};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use syslog_rfc5424::framing::FrameDecoder;
+use syslog_rfc5424::{parse_message_bytes_with_options, ParseOptions, Utf8Mode};
+
+// A minimal syslog-over-TCP server which prints every message it receives.
+//
+// Usage: tcp_server [address]
+//
+// The address defaults to 127.0.0.1:5140. Both octet-counted (RFC 6587 / RFC 5425) and
+// LF-terminated framing are accepted, and may even be mixed on one connection. Try it with
+//
+//     printf '<13>1 - - - - - - hello\n17 <14>1 - - - - - -' | nc 127.0.0.1 5140
+
+fn handle(mut stream: TcpStream) -> io::Result<()> {
+    let peer = stream.peer_addr()?;
+    // keep the original bytes of any MSG which isn't UTF-8
+    let opts = ParseOptions {
+        utf8_mode: Utf8Mode::LossyMsg,
+        ..Default::default()
+    };
+    let mut decoder = FrameDecoder::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        // a read may end anywhere, even in the middle of a length prefix; the decoder holds on
+        // to partial frames until the rest arrives
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        decoder.push(&buf[..n]);
+        while let Some(frame) = decoder.next_frame() {
+            match frame {
+                Ok(frame) => match parse_message_bytes_with_options(&frame, &opts) {
+                    Ok(msg) => println!("{}: {:?}", peer, msg),
+                    Err(e) => eprintln!("{}: unparseable message: {}", peer, e),
+                },
+                Err(e) => eprintln!("{}: framing error, skipping ahead: {}", peer, e),
+            }
+        }
+    }
+    if decoder.buffered_len() > 0 {
+        eprintln!(
+            "{}: connection closed with {} bytes of incomplete frame",
+            peer,
+            decoder.buffered_len()
+        );
+    }
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| String::from("127.0.0.1:5140"));
+    let listener = TcpListener::bind(&addr)?;
+    eprintln!("listening on {}", listener.local_addr()?);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        thread::spawn(move || {
+            if let Err(e) = handle(stream) {
+                eprintln!("connection error: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::process;
+
+use syslog_rfc5424::framing::{MessageFrames, ReadMessageError};
+use syslog_rfc5424::LineReport;
+
+// Check that every (non-empty) line of a file is a valid RFC 5424 message.
+//
+// Usage: validate_file [--json] <path>
+//
+// With --json (which needs the serde-serialize feature), one report object is printed per line
+// instead of just the final count.
+
+fn main() -> io::Result<()> {
+    let mut json = false;
+    let mut path = None;
+    for arg in env::args().skip(1) {
+        if arg == "--json" {
+            json = true;
+        } else {
+            path = Some(arg);
+        }
+    }
+    let path = match path {
+        Some(p) => p,
+        None => {
+            eprintln!("usage: validate_file [--json] <path>");
+            process::exit(2);
+        }
+    };
+    if json && !cfg!(feature = "serde-serialize") {
+        eprintln!("--json requires building with the serde-serialize feature");
+        process::exit(2);
+    }
+
+    let mut frames = MessageFrames::new(BufReader::new(File::open(path)?));
+    let mut total = 0;
+    let mut valid = 0;
+    while let Some(result) = frames.next() {
+        let error = match result {
+            Ok(_) => None,
+            Err(ReadMessageError::Parse { err, .. }) => Some(err.to_string()),
+            Err(e @ ReadMessageError::LineTooLong { .. }) => Some(e.to_string()),
+            Err(ReadMessageError::Io(e)) => return Err(e),
+        };
+        let report = LineReport {
+            line_number: frames.line_number(),
+            ok: error.is_none(),
+            error,
+        };
+        total += 1;
+        if report.ok {
+            valid += 1;
+        }
+        #[cfg(feature = "serde-serialize")]
+        {
+            if json {
+                println!("{}", serde_json::to_string(&report).unwrap());
+            }
+        }
+    }
+    if !json {
+        println!("{} of {} lines are valid", valid, total);
+    }
+    if valid != total {
+        process::exit(1);
+    }
+    Ok(())
+}
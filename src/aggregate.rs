@@ -0,0 +1,114 @@
+//! Severity and facility histograms over batches of messages.
+
+use std::collections::HashMap;
+
+use crate::facility::SyslogFacility;
+use crate::message::SyslogMessage;
+use crate::severity::SyslogSeverity;
+
+/// Counts of messages by severity and facility
+///
+/// Build one with `aggregate`, or record messages one at a time when processing a stream:
+///
+/// ```
+/// use syslog_rfc5424::aggregate::Aggregation;
+/// use syslog_rfc5424::{parse_message, SyslogSeverity};
+///
+/// let mut agg = Aggregation::default();
+/// for line in &["<11>1 - - - - - -", "<14>1 - - - - - -", "garbage"] {
+///     agg.record_result(&parse_message(line));
+/// }
+///
+/// assert_eq!(agg.total, 3);
+/// assert_eq!(agg.parse_errors, 1);
+/// assert_eq!(agg.by_severity[&SyslogSeverity::SEV_ERR], 1);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Aggregation {
+    pub by_severity: HashMap<SyslogSeverity, u64>,
+    pub by_facility: HashMap<SyslogFacility, u64>,
+    /// Everything recorded, including parse errors
+    pub total: u64,
+    pub parse_errors: u64,
+}
+
+impl Aggregation {
+    /// Count a successfully-parsed message
+    pub fn record(&mut self, msg: &SyslogMessage) {
+        *self.by_severity.entry(msg.severity).or_default() += 1;
+        *self.by_facility.entry(msg.facility).or_default() += 1;
+        self.total += 1;
+    }
+
+    /// Count an input which failed to parse
+    pub fn record_error(&mut self) {
+        self.parse_errors += 1;
+        self.total += 1;
+    }
+
+    /// Count the result of parsing an input, whether or not it succeeded
+    pub fn record_result<E>(&mut self, result: &Result<SyslogMessage, E>) {
+        match result {
+            Ok(msg) => self.record(msg),
+            Err(_) => self.record_error(),
+        }
+    }
+}
+
+impl Extend<SyslogMessage> for Aggregation {
+    fn extend<I: IntoIterator<Item = SyslogMessage>>(&mut self, msgs: I) {
+        for msg in msgs {
+            self.record(&msg);
+        }
+    }
+}
+
+/// Count a batch of messages by severity and facility
+pub fn aggregate<I: IntoIterator<Item = SyslogMessage>>(msgs: I) -> Aggregation {
+    let mut agg = Aggregation::default();
+    agg.extend(msgs);
+    agg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{aggregate, Aggregation};
+    use crate::facility::SyslogFacility;
+    use crate::parser::parse_message;
+    use crate::severity::SyslogSeverity;
+
+    #[test]
+    fn test_aggregate() {
+        let msgs = [
+            "<11>1 - - - - - -",
+            "<14>1 - - - - - -",
+            "<12>1 - - - - - -",
+            "<27>1 - - - - - -",
+        ]
+        .iter()
+        .map(|m| parse_message(m).unwrap());
+        let agg = aggregate(msgs);
+        assert_eq!(agg.total, 4);
+        assert_eq!(agg.parse_errors, 0);
+        assert_eq!(agg.by_severity[&SyslogSeverity::SEV_ERR], 2);
+        assert_eq!(agg.by_severity[&SyslogSeverity::SEV_INFO], 1);
+        assert_eq!(agg.by_severity[&SyslogSeverity::SEV_WARNING], 1);
+        assert_eq!(agg.by_facility[&SyslogFacility::LOG_USER], 3);
+        assert_eq!(agg.by_facility[&SyslogFacility::LOG_DAEMON], 1);
+    }
+
+    #[test]
+    fn test_fold() {
+        let agg = ["<11>1 - - - - - -", "<1>", "<11>1 - - - - - -"]
+            .iter()
+            .map(parse_message)
+            .fold(Aggregation::default(), |mut agg, res| {
+                agg.record_result(&res);
+                agg
+            });
+        assert_eq!(agg.total, 3);
+        assert_eq!(agg.parse_errors, 1);
+        assert_eq!(agg.by_severity.len(), 1);
+        assert_eq!(agg.by_severity[&SyslogSeverity::SEV_ERR], 2);
+    }
+}
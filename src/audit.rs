@@ -0,0 +1,139 @@
+//! Typed access to Linux-audit-style (`type=SYSCALL msg=audit(…): key=val …`) message bodies.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use core::str::FromStr;
+
+/// The fields of an audit record, as found in the MSG of a syslog message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditFields {
+    /// The record type, e.g. `SYSCALL` or `USER_LOGIN`
+    pub record_type: String,
+    /// Seconds since the Unix epoch from the `audit(…)` stamp
+    pub timestamp: i64,
+    /// Milliseconds part of the `audit(…)` stamp
+    pub timestamp_millis: u32,
+    /// Serial number from the `audit(…)` stamp; records with the same timestamp and serial
+    /// belong to the same event
+    pub serial: u64,
+    /// The remaining `key=value` pairs (plus `node`, if the record was prefixed with one), with
+    /// any surrounding quotes removed
+    pub fields: BTreeMap<String, String>,
+}
+
+/// Split off one `key=value` pair, where the value may be wrapped in double or single quotes
+fn take_pair(input: &str) -> Option<((&str, &str), &str)> {
+    let eq = input.find('=')?;
+    let key = &input[..eq];
+    if key.is_empty() || key.contains(|c: char| c.is_ascii_whitespace()) {
+        return None;
+    }
+    let rest = &input[eq + 1..];
+    let (value, rest) = match rest.chars().next() {
+        Some(q) if q == '"' || q == '\'' => {
+            let end = rest[1..].find(q)?;
+            (&rest[1..end + 1], &rest[end + 2..])
+        }
+        _ => {
+            let end = rest
+                .find(|c: char| c.is_ascii_whitespace())
+                .unwrap_or(rest.len());
+            (&rest[..end], &rest[end..])
+        }
+    };
+    if !(rest.is_empty() || rest.starts_with(|c: char| c.is_ascii_whitespace())) {
+        return None;
+    }
+    Some(((key, value), rest.trim_start()))
+}
+
+/// Parse the `audit(1364481363.243:24287)` stamp into seconds, milliseconds, and serial
+fn parse_stamp(stamp: &str) -> Option<(i64, u32, u64)> {
+    let stamp = stamp.strip_prefix("audit(")?.strip_suffix(')')?;
+    let (time, serial) = stamp.split_once(':')?;
+    let (secs, millis) = time.split_once('.')?;
+    let all_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !all_digits(secs) || !all_digits(millis) || !all_digits(serial) {
+        return None;
+    }
+    Some((
+        i64::from_str(secs).ok()?,
+        u32::from_str(millis).ok()?,
+        u64::from_str(serial).ok()?,
+    ))
+}
+
+pub(crate) fn parse(input: &str) -> Option<AuditFields> {
+    let mut fields = BTreeMap::new();
+    let mut rest = input.trim_start();
+    if rest.starts_with("node=") {
+        let ((key, value), r) = take_pair(rest)?;
+        fields.insert(String::from(key), String::from(value));
+        rest = r;
+    }
+    let ((key, record_type), r) = take_pair(rest)?;
+    if key != "type" || record_type.is_empty() {
+        return None;
+    }
+    let r = r.strip_prefix("msg=")?;
+    let end = r.find("):")? + 1;
+    let (timestamp, timestamp_millis, serial) = parse_stamp(&r[..end])?;
+    rest = &r[end + 1..];
+    if !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    rest = rest.trim_start();
+    while !rest.is_empty() {
+        let ((key, value), r) = take_pair(rest)?;
+        fields.insert(String::from(key), String::from(value));
+        rest = r;
+    }
+    Some(AuditFields {
+        record_type: String::from(record_type),
+        timestamp,
+        timestamp_millis,
+        serial,
+        fields,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn test_syscall() {
+        let fields = parse(
+            r#"type=SYSCALL msg=audit(1364481363.243:24287): arch=c000003e syscall=2 success=no comm="cat" exe="/usr/bin/cat""#,
+        )
+        .expect("should be an audit record");
+        assert_eq!(fields.record_type, "SYSCALL");
+        assert_eq!(fields.timestamp, 1364481363);
+        assert_eq!(fields.timestamp_millis, 243);
+        assert_eq!(fields.serial, 24287);
+        assert_eq!(fields.fields["syscall"], "2");
+        assert_eq!(fields.fields["comm"], "cat");
+        assert_eq!(fields.fields["exe"], "/usr/bin/cat");
+    }
+
+    #[test]
+    fn test_node_and_single_quotes() {
+        let fields = parse(
+            "node=web1 type=USER_LOGIN msg=audit(1500000000.001:7): pid=42 msg='op=login acct=\"root\" res=success'",
+        )
+        .expect("should be an audit record");
+        assert_eq!(fields.record_type, "USER_LOGIN");
+        assert_eq!(fields.fields["node"], "web1");
+        assert_eq!(fields.fields["pid"], "42");
+        assert_eq!(fields.fields["msg"], "op=login acct=\"root\" res=success");
+    }
+
+    #[test]
+    fn test_not_audit() {
+        assert!(parse("").is_none());
+        assert!(parse("type=SYSCALL but not really").is_none());
+        assert!(parse("level=info msg=hello").is_none());
+        assert!(parse("type=SYSCALL msg=audit(12.x:3): a=b").is_none());
+        assert!(parse("type=SYSCALL msg=audit(12.5:3): a=\"unterminated").is_none());
+    }
+}
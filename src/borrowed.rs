@@ -0,0 +1,244 @@
+//! Messages which borrow their text from the input rather than copying it.
+
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+
+use crate::facility;
+use crate::message::{
+    name_t, time_t, SDParamIDType, StructuredData, StructuredDataMap, SyslogMessage,
+};
+use crate::parser::{self, ParseContext, SdSink};
+use crate::severity;
+
+/// The params of one element of a `StructuredDataRef`, by name
+pub type StructuredDataElementRef<'a> = BTreeMap<Cow<'a, str>, Cow<'a, str>>;
+
+/// Structured data which borrows from the input; see `parser::parse_message_ref`
+///
+/// Like `StructuredData`, this keeps only the last value of a repeated param. SDIDs and param
+/// names are always borrowed, and so are values unless they contain escapes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StructuredDataRef<'a> {
+    elements: BTreeMap<Cow<'a, str>, StructuredDataElementRef<'a>>,
+}
+
+impl<'a> StructuredDataRef<'a> {
+    /// Lookup by SDID, SDParamID pair
+    pub fn find_tuple(&self, sd_id: &str, sd_param_id: &str) -> Option<&str> {
+        self.elements
+            .get(sd_id)?
+            .get(sd_param_id)
+            .map(|value| &**value)
+    }
+
+    /// Find all param/value mappings for a given SDID
+    pub fn find_sdid(&self, sd_id: &str) -> Option<&StructuredDataElementRef<'a>> {
+        self.elements.get(sd_id)
+    }
+
+    /// Iterate over the elements, sorted by SDID
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &StructuredDataElementRef<'a>)> {
+        self.elements
+            .iter()
+            .map(|(sd_id, element)| (&**sd_id, element))
+    }
+
+    /// The number of distinct SD_IDs
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Whether or not this is empty
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Copy everything into an owned `StructuredData`
+    pub fn to_owned(&self) -> StructuredData {
+        let mut sd = StructuredData::default();
+        for (sd_id, element) in &self.elements {
+            StructuredDataMap::add_element(&mut sd, sd_id);
+            for (sd_param_id, sd_param_value) in element {
+                StructuredDataMap::add_param(
+                    &mut sd,
+                    sd_id,
+                    SDParamIDType::from(&**sd_param_id),
+                    String::from(&**sd_param_value),
+                );
+            }
+        }
+        sd
+    }
+
+    fn into_static(self) -> StructuredDataRef<'static> {
+        let owned = |s: Cow<str>| Cow::Owned(s.into_owned());
+        StructuredDataRef {
+            elements: self
+                .elements
+                .into_iter()
+                .map(|(sd_id, element)| {
+                    let element = element
+                        .into_iter()
+                        .map(|(name, value)| (owned(name), owned(value)))
+                        .collect();
+                    (owned(sd_id), element)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<'a> SdSink<'a> for StructuredDataRef<'a> {
+    fn add_element(&mut self, sd_id: &'a str, _ctx: &mut ParseContext) {
+        self.elements.entry(Cow::Borrowed(sd_id)).or_default();
+    }
+
+    fn contains_param(&self, sd_id: &str, sd_param_id: &str) -> bool {
+        self.elements
+            .get(sd_id)
+            .is_some_and(|element| element.contains_key(sd_param_id))
+    }
+
+    fn add_param(
+        &mut self,
+        sd_id: &'a str,
+        sd_param_id: Cow<'a, str>,
+        sd_param_value: Cow<'a, str>,
+        _ctx: &mut ParseContext,
+    ) {
+        self.elements
+            .entry(Cow::Borrowed(sd_id))
+            .or_default()
+            .insert(sd_param_id, sd_param_value);
+    }
+}
+
+/// A RFC5424-protocol syslog message which borrows its text from the input
+///
+/// This is what `parser::parse_message_ref` returns. It has the same fields as `SyslogMessage`,
+/// but nothing is copied out of the input unless it has to be changed on the way (SD param
+/// values with escapes in them, or input which was decoded first because of
+/// `ParseOptions::input_encoding`). Use `to_owned` to get a `SyslogMessage` once the input is
+/// going away.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyslogMessageRef<'a> {
+    pub severity: severity::SyslogSeverity,
+    pub facility: facility::SyslogFacility,
+    pub version: i32,
+    pub timestamp: Option<time_t>,
+    pub timestamp_nanos: Option<u32>,
+    /// Whether the timestamp's offset was `-00:00`; see `SyslogMessage::timestamp_offset_unknown`
+    pub timestamp_offset_unknown: bool,
+    /// The UTC offset the timestamp was given in, in seconds east of UTC
+    pub timestamp_offset_secs: Option<i32>,
+    pub hostname: Option<Cow<'a, str>>,
+    pub appname: Option<Cow<'a, str>>,
+    /// The PROCID as written; `to_owned` turns it into a `ProcId`
+    pub procid: Option<Cow<'a, str>>,
+    pub msgid: Option<Cow<'a, str>>,
+    pub sd: StructuredDataRef<'a>,
+    pub msg: Cow<'a, str>,
+    /// Whether the MSG started with a BOM; see `SyslogMessage::msg_has_bom`
+    pub msg_has_bom: bool,
+    /// Transport metadata split off the end of the MSG (see
+    /// `ParseOptions::trailing_field_delimiter`)
+    pub trailer: Option<Cow<'a, str>>,
+}
+
+impl SyslogMessageRef<'_> {
+    /// Copy everything into an owned `SyslogMessage`, as `parser::parse_message` would have
+    /// returned
+    pub fn to_owned(&self) -> SyslogMessage {
+        SyslogMessage {
+            severity: self.severity,
+            facility: self.facility,
+            version: self.version,
+            timestamp: self.timestamp,
+            timestamp_nanos: self.timestamp_nanos,
+            timestamp_offset_unknown: self.timestamp_offset_unknown,
+            timestamp_offset_secs: self.timestamp_offset_secs,
+            hostname: self.hostname.as_deref().map(name_t::from),
+            appname: self.appname.as_deref().map(name_t::from),
+            procid: self.procid.as_deref().map(parser::parse_procid),
+            msgid: self.msgid.as_deref().map(String::from),
+            sd: self.sd.to_owned(),
+            msg: String::from(&*self.msg),
+            msg_has_bom: self.msg_has_bom,
+            msg_raw: None,
+            trailer: self.trailer.as_deref().map(String::from),
+        }
+    }
+
+    /// Take ownership of everything still borrowed, for when the input was a temporary
+    pub(crate) fn into_static(self) -> SyslogMessageRef<'static> {
+        let owned = |s: Option<Cow<str>>| s.map(|s| Cow::Owned(s.into_owned()));
+        SyslogMessageRef {
+            severity: self.severity,
+            facility: self.facility,
+            version: self.version,
+            timestamp: self.timestamp,
+            timestamp_nanos: self.timestamp_nanos,
+            timestamp_offset_unknown: self.timestamp_offset_unknown,
+            timestamp_offset_secs: self.timestamp_offset_secs,
+            hostname: owned(self.hostname),
+            appname: owned(self.appname),
+            procid: owned(self.procid),
+            msgid: owned(self.msgid),
+            sd: self.sd.into_static(),
+            msg: Cow::Owned(self.msg.into_owned()),
+            msg_has_bom: self.msg_has_bom,
+            trailer: owned(self.trailer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::parser::{parse_message, parse_message_ref, parse_message_ref_with_options};
+    use crate::{InputEncoding, ParseOptions};
+
+    #[test]
+    fn test_borrows_from_input() {
+        let input = r#"<78>1 2016-01-15T00:04:01Z host1 CROND 10391 - [meta sequenceId="29"][my key="a \"quoted\" value"] some_message"#;
+        let msg = parse_message_ref(input).unwrap();
+        assert!(matches!(msg.hostname, Some(Cow::Borrowed("host1"))));
+        assert!(matches!(msg.msg, Cow::Borrowed("some_message")));
+        assert_eq!(msg.sd.find_tuple("meta", "sequenceId"), Some("29"));
+        assert!(matches!(
+            msg.sd.find_sdid("meta").unwrap()["sequenceId"],
+            Cow::Borrowed(_)
+        ));
+        // only the escaped value had to be copied
+        assert!(matches!(
+            msg.sd.find_sdid("my").unwrap()["key"],
+            Cow::Owned(_)
+        ));
+        assert_eq!(msg.sd.find_tuple("my", "key"), Some(r#"a "quoted" value"#));
+
+        assert_eq!(msg.to_owned(), parse_message(input).unwrap());
+    }
+
+    #[test]
+    fn test_decoded_input() {
+        let opts = ParseOptions {
+            input_encoding: InputEncoding::Percent,
+            ..Default::default()
+        };
+        let msg =
+            parse_message_ref_with_options("<1>1%20-%20host%20-%20-%20-%20-%20hi", &opts).unwrap();
+        assert_eq!(msg.hostname.as_deref(), Some("host"));
+        assert_eq!(msg.msg, "hi");
+    }
+
+    #[test]
+    fn test_errors_match() {
+        let input = "<1>1 - host";
+        assert_eq!(
+            parse_message_ref(input).unwrap_err().offset(),
+            parse_message(input).unwrap_err().offset()
+        );
+    }
+}
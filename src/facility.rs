@@ -1,11 +1,13 @@
 #[cfg(feature = "serde-serialize")]
-use serde::{Serialize, Serializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
-use std::convert::TryFrom;
+use core::convert::TryFrom;
+use core::fmt;
+use core::str::FromStr;
 
 use thiserror::Error;
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Ord, PartialOrd)]
 #[allow(non_camel_case_types)]
 /// Syslog facilities. Taken From RFC 5424, but I've heard that some platforms mix these around.
 /// Names are from Linux.
@@ -36,10 +38,12 @@ pub enum SyslogFacility {
     LOG_LOCAL7 = 23,
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Error, PartialEq, Eq)]
 pub enum SyslogFacilityError {
     #[error("integer does not correspond to a known facility")]
     InvalidInteger,
+    #[error("string does not correspond to a known facility")]
+    InvalidName,
 }
 
 impl TryFrom<i32> for SyslogFacility {
@@ -77,12 +81,30 @@ impl TryFrom<i32> for SyslogFacility {
     }
 }
 
+impl FromStr for SyslogFacility {
+    type Err = SyslogFacilityError;
+
+    /// Parse one of the names produced by `as_str` (such as `"cron"` or `"local0"`), ignoring
+    /// ASCII case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        (0..=23)
+            .filter_map(Self::from_int)
+            .find(|f| f.as_str().eq_ignore_ascii_case(s))
+            .ok_or(SyslogFacilityError::InvalidName)
+    }
+}
+
 impl SyslogFacility {
     /// Convert an int (as used in the wire serialization) into a `SyslogFacility`
     pub(crate) fn from_int(i: i32) -> Option<Self> {
         Self::try_from(i).ok()
     }
 
+    /// The facility's numeric code, as used in the PRI
+    pub fn as_int(self) -> i32 {
+        self as i32
+    }
+
     /// Convert a syslog facility into a unique string representation
     pub fn as_str(self) -> &'static str {
         match self {
@@ -112,6 +134,61 @@ impl SyslogFacility {
             SyslogFacility::LOG_LOCAL7 => "local7",
         }
     }
+
+    /// A human-readable description, as given in Table 1 of RFC 5424
+    pub fn description(self) -> &'static str {
+        match self {
+            SyslogFacility::LOG_KERN => "Kernel messages",
+            SyslogFacility::LOG_USER => "User-level messages",
+            SyslogFacility::LOG_MAIL => "Mail system",
+            SyslogFacility::LOG_DAEMON => "System daemons",
+            SyslogFacility::LOG_AUTH => "Security/authorization messages",
+            SyslogFacility::LOG_SYSLOG => "Messages generated internally by syslogd",
+            SyslogFacility::LOG_LPR => "Line printer subsystem",
+            SyslogFacility::LOG_NEWS => "Network news subsystem",
+            SyslogFacility::LOG_UUCP => "UUCP subsystem",
+            SyslogFacility::LOG_CRON => "Clock daemon",
+            SyslogFacility::LOG_AUTHPRIV => "Security/authorization messages",
+            SyslogFacility::LOG_FTP => "FTP daemon",
+            SyslogFacility::LOG_NTP => "NTP subsystem",
+            SyslogFacility::LOG_AUDIT => "Log audit",
+            SyslogFacility::LOG_ALERT => "Log alert",
+            SyslogFacility::LOG_CLOCKD => "Clock daemon",
+            SyslogFacility::LOG_LOCAL0 => "Local use 0 (local0)",
+            SyslogFacility::LOG_LOCAL1 => "Local use 1 (local1)",
+            SyslogFacility::LOG_LOCAL2 => "Local use 2 (local2)",
+            SyslogFacility::LOG_LOCAL3 => "Local use 3 (local3)",
+            SyslogFacility::LOG_LOCAL4 => "Local use 4 (local4)",
+            SyslogFacility::LOG_LOCAL5 => "Local use 5 (local5)",
+            SyslogFacility::LOG_LOCAL6 => "Local use 6 (local6)",
+            SyslogFacility::LOG_LOCAL7 => "Local use 7 (local7)",
+        }
+    }
+}
+
+impl PartialEq<str> for SyslogFacility {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for SyslogFacility {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Display for SyslogFacility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for SyslogFacility {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self::from_int(u.int_in_range(0..=23)?).expect("all of 0..=23 are facilities"))
+    }
 }
 
 #[cfg(feature = "serde-serialize")]
@@ -121,12 +198,71 @@ impl Serialize for SyslogFacility {
     }
 }
 
+#[cfg(feature = "serde-serialize")]
+impl<'de> Deserialize<'de> for SyslogFacility {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        alloc::string::String::deserialize(de)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::SyslogFacility;
+    use std::str::FromStr;
+
+    use super::{SyslogFacility, SyslogFacilityError};
 
     #[test]
     fn test_deref() {
         assert_eq!(SyslogFacility::LOG_KERN.as_str(), "kern");
     }
+
+    #[test]
+    fn test_as_int() {
+        for i in 0..=23 {
+            assert_eq!(SyslogFacility::from_int(i).unwrap().as_int(), i);
+        }
+    }
+
+    #[test]
+    fn test_description() {
+        assert_eq!(SyslogFacility::LOG_KERN.description(), "Kernel messages");
+        assert_eq!(
+            SyslogFacility::LOG_LOCAL7.description(),
+            "Local use 7 (local7)"
+        );
+    }
+
+    #[test]
+    fn test_str_eq() {
+        assert_eq!(SyslogFacility::LOG_CRON, "cron");
+        assert!(SyslogFacility::LOG_CRON == *"cron");
+        assert_ne!(SyslogFacility::LOG_CRON, "LOG_CRON");
+    }
+
+    #[test]
+    fn test_display() {
+        for f in (0..=23).filter_map(SyslogFacility::from_int) {
+            assert_eq!(f.to_string(), f.as_str());
+        }
+        assert_eq!(format!("{}", SyslogFacility::LOG_LOCAL3), "local3");
+    }
+
+    #[test]
+    fn test_from_str() {
+        for f in (0..=23).filter_map(SyslogFacility::from_int) {
+            assert_eq!(SyslogFacility::from_str(f.as_str()), Ok(f));
+        }
+        assert_eq!("LOCAL3".parse(), Ok(SyslogFacility::LOG_LOCAL3));
+        assert_eq!("Cron".parse(), Ok(SyslogFacility::LOG_CRON));
+        assert_eq!(
+            "LOG_CRON".parse::<SyslogFacility>(),
+            Err(SyslogFacilityError::InvalidName)
+        );
+        assert_eq!(
+            "".parse::<SyslogFacility>(),
+            Err(SyslogFacilityError::InvalidName)
+        );
+    }
 }
@@ -0,0 +1,519 @@
+//! Splitting a byte stream (e.g. from a TCP connection) into individual syslog messages.
+//!
+//! Two framings are in common use (see RFC 6587): *octet counting*, where each message is
+//! preceded by its length in bytes and a space (`17 <1>1 - - - - - -`), and *non-transparent
+//! framing*, where each message is terminated by a LF. `FrameDecoder` detects which one is in
+//! use frame by frame; `MessageFrames` reads LF-terminated messages from anything implementing
+//! `BufRead`.
+
+use std::io::{self, BufRead, Read};
+
+use thiserror::Error;
+
+use crate::message::SyslogMessage;
+use crate::parser::{parse_message_bytes_with_options, ParseErr, ParseOptions, Utf8Mode};
+
+/// The largest frame accepted by `FrameDecoder::new`, and the longest line accepted by
+/// `MessageFrames::new`
+pub const DEFAULT_MAX_FRAME_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum FramingError {
+    #[error("frame is longer than the maximum of {0} bytes")]
+    FrameTooLong(usize),
+    #[error("unexpected byte {0:#04x} in frame header")]
+    InvalidFrameStart(u8),
+}
+
+#[derive(Debug, Error)]
+pub enum ReadMessageError {
+    #[error("error reading messages: {0}")]
+    Io(#[from] io::Error),
+    #[error("line {line_number}: {err}")]
+    Parse {
+        line_number: usize,
+        #[source]
+        err: ParseErr,
+    },
+    #[error("line {line_number} is longer than the maximum of {max_line_len} bytes")]
+    LineTooLong {
+        line_number: usize,
+        max_line_len: usize,
+    },
+}
+
+/// A push-based decoder which splits a stream of bytes into frames
+///
+/// Feed it bytes as they arrive with `push`, then call `next_frame` until it returns `None`.
+/// Frames are never allowed to grow beyond `max_frame_len`, so a corrupt length prefix can't
+/// make the decoder wait forever or buffer unbounded amounts of data. After an error, the
+/// decoder skips ahead to the next plausible frame start (a `<`, possibly preceded by an octet
+/// count) and carries on.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::framing::FrameDecoder;
+///
+/// let mut decoder = FrameDecoder::new();
+/// decoder.push(b"16 <1>1 - - - - - -<2>1 - - - - - -\n<3>1 - -");
+///
+/// assert_eq!(decoder.next_frame(), Some(Ok(b"<1>1 - - - - - -".to_vec())));
+/// assert_eq!(decoder.next_frame(), Some(Ok(b"<2>1 - - - - - -".to_vec())));
+/// // the last message is incomplete
+/// assert_eq!(decoder.next_frame(), None);
+/// ```
+#[derive(Debug)]
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+    max_frame_len: usize,
+    /// Set when the buffer was exhausted while looking for the next frame start
+    resyncing: bool,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameDecoder {
+    /// Create a decoder accepting frames of up to `DEFAULT_MAX_FRAME_LEN` bytes
+    pub fn new() -> Self {
+        Self::with_max_frame_len(DEFAULT_MAX_FRAME_LEN)
+    }
+
+    /// Create a decoder accepting frames of up to `max_frame_len` bytes
+    pub fn with_max_frame_len(max_frame_len: usize) -> Self {
+        FrameDecoder {
+            buf: Vec::new(),
+            max_frame_len,
+            resyncing: false,
+        }
+    }
+
+    /// Append newly-received bytes to the decoder's buffer
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// The number of bytes buffered but not yet returned as part of a frame
+    pub fn buffered_len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Take the next complete frame out of the buffer
+    ///
+    /// Returns `None` if more data is needed. An error describes a corrupt frame which has been
+    /// skipped; decoding can continue by calling `next_frame` again.
+    pub fn next_frame(&mut self) -> Option<Result<Vec<u8>, FramingError>> {
+        if self.resyncing && !self.resync(0) {
+            return None;
+        }
+        // tolerate stray line terminators between frames
+        let skip = self
+            .buf
+            .iter()
+            .take_while(|b| **b == b'\n' || **b == b'\r')
+            .count();
+        self.buf.drain(..skip);
+        match *self.buf.first()? {
+            b'0'..=b'9' => self.octet_counted(),
+            b'<' => self.lf_terminated(),
+            b => Some(Err(self.fail(1, FramingError::InvalidFrameStart(b)))),
+        }
+    }
+
+    fn octet_counted(&mut self) -> Option<Result<Vec<u8>, FramingError>> {
+        let digits = self.buf.iter().take_while(|b| b.is_ascii_digit()).count();
+        let max_digits = self.max_frame_len.to_string().len();
+        if digits > max_digits {
+            return Some(Err(
+                self.fail(digits, FramingError::FrameTooLong(self.max_frame_len))
+            ));
+        }
+        let sep = *self.buf.get(digits)?;
+        if sep != b' ' {
+            return Some(Err(self.fail(digits, FramingError::InvalidFrameStart(sep))));
+        }
+        let len = std::str::from_utf8(&self.buf[..digits])
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(usize::MAX);
+        if len > self.max_frame_len {
+            return Some(Err(
+                self.fail(digits + 1, FramingError::FrameTooLong(self.max_frame_len))
+            ));
+        }
+        let end = digits + 1 + len;
+        if self.buf.len() < end {
+            return None;
+        }
+        let frame = self.buf[digits + 1..end].to_vec();
+        self.buf.drain(..end);
+        Some(Ok(frame))
+    }
+
+    fn lf_terminated(&mut self) -> Option<Result<Vec<u8>, FramingError>> {
+        match self.buf.iter().position(|b| *b == b'\n') {
+            Some(idx) if idx <= self.max_frame_len => {
+                let mut frame: Vec<u8> = self.buf.drain(..=idx).collect();
+                frame.pop();
+                if frame.last() == Some(&b'\r') {
+                    frame.pop();
+                }
+                Some(Ok(frame))
+            }
+            None if self.buf.len() <= self.max_frame_len => None,
+            _ => Some(Err(
+                self.fail(1, FramingError::FrameTooLong(self.max_frame_len))
+            )),
+        }
+    }
+
+    /// Skip the first `skip` bytes of the current (corrupt) frame and resynchronize, then
+    /// return `err`
+    fn fail(&mut self, skip: usize, err: FramingError) -> FramingError {
+        self.resync(skip);
+        err
+    }
+
+    /// Discard bytes from `from` up to the next plausible frame start, returning whether one was
+    /// found. If not, the whole buffer is dropped and the search continues on the next call.
+    fn resync(&mut self, from: usize) -> bool {
+        let from = from.min(self.buf.len());
+        match self.buf[from..].iter().position(|b| *b == b'<') {
+            Some(pos) => {
+                let mut start = from + pos;
+                // keep an octet count immediately preceding the `<`
+                if start > from && self.buf[start - 1] == b' ' {
+                    let digits = self.buf[from..start - 1]
+                        .iter()
+                        .rev()
+                        .take_while(|b| b.is_ascii_digit())
+                        .count();
+                    if digits > 0 {
+                        start -= digits + 1;
+                    }
+                }
+                self.buf.drain(..start);
+                self.resyncing = false;
+                true
+            }
+            None => {
+                self.buf.clear();
+                self.resyncing = true;
+                false
+            }
+        }
+    }
+}
+
+/// An iterator over the LF-terminated messages read from a `BufRead`
+///
+/// Each line (without its `\n` or `\r\n`) is parsed as one message; empty lines are skipped. A
+/// line which doesn't parse yields a `ReadMessageError::Parse` and iteration carries on with the
+/// next line, whereas an I/O error ends the iteration. Lines are never buffered beyond
+/// `max_line_len` bytes (not counting the line terminator), so a sender which never sends a
+/// `\n` can't exhaust memory; a longer line yields a `ReadMessageError::LineTooLong` and the
+/// rest of it is skipped.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::framing::MessageFrames;
+///
+/// let input = &b"<1>1 - - - - - - one\n\n<1>1 - two\n<1>1 - - - - - - three\n"[..];
+/// let results: Vec<_> = MessageFrames::new(input).collect();
+///
+/// assert_eq!(results.len(), 3);
+/// assert_eq!(results[0].as_ref().unwrap().msg, "one");
+/// assert!(results[1].is_err());
+/// assert_eq!(results[2].as_ref().unwrap().msg, "three");
+/// ```
+#[derive(Debug)]
+pub struct MessageFrames<R> {
+    reader: R,
+    opts: ParseOptions,
+    buf: Vec<u8>,
+    max_line_len: usize,
+    line_number: usize,
+    done: bool,
+}
+
+impl<R: BufRead> MessageFrames<R> {
+    /// Read messages from `reader`, parsing them as `parse_message_bytes` does: with the default
+    /// `ParseOptions`, except that a MSG which isn't valid UTF-8 is kept in `msg_raw`
+    /// (`Utf8Mode::LossyMsg`)
+    pub fn new(reader: R) -> Self {
+        let opts = ParseOptions {
+            utf8_mode: Utf8Mode::LossyMsg,
+            ..Default::default()
+        };
+        Self::with_options(reader, opts)
+    }
+
+    /// Read messages from `reader`, parsing them with the given `ParseOptions`
+    pub fn with_options(reader: R, opts: ParseOptions) -> Self {
+        MessageFrames {
+            reader,
+            opts,
+            buf: Vec::new(),
+            max_line_len: DEFAULT_MAX_FRAME_LEN,
+            line_number: 0,
+            done: false,
+        }
+    }
+
+    /// Accept lines of up to `max_line_len` bytes, rather than `DEFAULT_MAX_FRAME_LEN`
+    pub fn max_line_len(mut self, max_line_len: usize) -> Self {
+        self.max_line_len = max_line_len;
+        self
+    }
+
+    /// The 1-based number of the line which produced the most recent item, counting skipped
+    /// empty lines; 0 before the first item
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// Give back the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Discard input up to and including the next `\n`, or to the end of the input
+    fn skip_line(&mut self) -> io::Result<()> {
+        loop {
+            let (found, used) = {
+                let available = match self.reader.fill_buf() {
+                    Ok(available) => available,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+                match available.iter().position(|b| *b == b'\n') {
+                    Some(idx) => (true, idx + 1),
+                    None => (available.is_empty(), available.len()),
+                }
+            };
+            self.reader.consume(used);
+            if found {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for MessageFrames<R> {
+    type Item = Result<SyslogMessage, ReadMessageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.buf.clear();
+            // room for the longest allowed line and its `\r\n`
+            let limit = (self.max_line_len as u64).saturating_add(2);
+            match (&mut self.reader)
+                .take(limit)
+                .read_until(b'\n', &mut self.buf)
+            {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.line_number += 1;
+                    if self.buf.len() > self.max_line_len && self.buf.last() != Some(&b'\n') {
+                        if let Err(e) = self.skip_line() {
+                            self.done = true;
+                            return Some(Err(e.into()));
+                        }
+                    }
+                    let mut line = &self.buf[..];
+                    if let Some(l) = line.strip_suffix(b"\n") {
+                        line = l.strip_suffix(b"\r").unwrap_or(l);
+                    }
+                    if line.len() > self.max_line_len {
+                        return Some(Err(ReadMessageError::LineTooLong {
+                            line_number: self.line_number,
+                            max_line_len: self.max_line_len,
+                        }));
+                    }
+                    if line.is_empty() {
+                        continue;
+                    }
+                    return Some(parse_message_bytes_with_options(line, &self.opts).map_err(
+                        |err| ReadMessageError::Parse {
+                            line_number: self.line_number,
+                            err,
+                        },
+                    ));
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, BufReader, Read};
+
+    use super::{FrameDecoder, FramingError, MessageFrames, ReadMessageError};
+
+    #[test]
+    fn test_octet_counted_split_reads() {
+        let mut decoder = FrameDecoder::new();
+        decoder.push(b"2");
+        assert_eq!(decoder.next_frame(), None);
+        decoder.push(b"2 <1>1 - - - - - - hel");
+        assert_eq!(decoder.next_frame(), None);
+        decoder.push(b"lo18 <1>1 - - - - - - ");
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Ok(b"<1>1 - - - - - - hello".to_vec()))
+        );
+        assert_eq!(decoder.next_frame(), None);
+        decoder.push(b"x");
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Ok(b"<1>1 - - - - - - x".to_vec()))
+        );
+        assert_eq!(decoder.buffered_len(), 0);
+    }
+
+    #[test]
+    fn test_lf_terminated() {
+        let mut decoder = FrameDecoder::new();
+        decoder.push(b"<1>1 - - - - - - one\r\n\n<1>1 - - - - - - two\n");
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Ok(b"<1>1 - - - - - - one".to_vec()))
+        );
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Ok(b"<1>1 - - - - - - two".to_vec()))
+        );
+        assert_eq!(decoder.next_frame(), None);
+    }
+
+    #[test]
+    fn test_bogus_huge_length_prefix() {
+        let mut decoder = FrameDecoder::with_max_frame_len(1024);
+        decoder.push(b"999999999 <1>1 - - - - - - corrupt 16 <2>1 - - - - - -");
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Err(FramingError::FrameTooLong(1024)))
+        );
+        // the `<` right after the bogus count looks like an LF-terminated frame
+        decoder.push(b"\n");
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Ok(b"<1>1 - - - - - - corrupt 16 <2>1 - - - - - -".to_vec()))
+        );
+
+        let mut decoder = FrameDecoder::with_max_frame_len(1024);
+        decoder.push(b"99999999999999999999999 garbage 16 <2>1 - - - - - -");
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Err(FramingError::FrameTooLong(1024)))
+        );
+        assert_eq!(decoder.next_frame(), Some(Ok(b"<2>1 - - - - - -".to_vec())));
+    }
+
+    #[test]
+    fn test_resync_across_pushes() {
+        let mut decoder = FrameDecoder::with_max_frame_len(16);
+        decoder.push(b"garbage without a frame start");
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Err(FramingError::InvalidFrameStart(b'g')))
+        );
+        assert_eq!(decoder.next_frame(), None);
+        assert_eq!(decoder.buffered_len(), 0);
+        decoder.push(b"more garbage<1>1 - - - - - -\n");
+        assert_eq!(decoder.next_frame(), Some(Ok(b"<1>1 - - - - - -".to_vec())));
+    }
+
+    #[test]
+    fn test_lf_frame_too_long() {
+        let mut decoder = FrameDecoder::with_max_frame_len(16);
+        decoder.push(b"<1>1 - - - - - - this is far too long");
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Err(FramingError::FrameTooLong(16)))
+        );
+        assert_eq!(decoder.next_frame(), None);
+    }
+
+    #[test]
+    fn test_message_frames() {
+        let input = "<1>1 - - - - - - one\r\n\n\r\n<1>1 - - - - - - two\nnot syslog\n<1>1 - - - - - - three";
+        let mut frames = MessageFrames::new(input.as_bytes());
+        assert_eq!(frames.next().unwrap().unwrap().msg, "one");
+        assert_eq!(frames.line_number(), 1);
+        assert_eq!(frames.next().unwrap().unwrap().msg, "two");
+        assert_eq!(frames.line_number(), 4);
+        match frames.next() {
+            Some(Err(ReadMessageError::Parse { line_number, .. })) => assert_eq!(line_number, 5),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        // no trailing newline on the last line
+        assert_eq!(frames.next().unwrap().unwrap().msg, "three");
+        assert!(frames.next().is_none());
+        assert!(frames.next().is_none());
+        // a binary MSG is kept
+        let mut frames = MessageFrames::new(&b"<1>1 - - - - - - \x1f\x8b\n"[..]);
+        let msg = frames.next().unwrap().unwrap();
+        assert_eq!(msg.msg_raw.as_deref(), Some(&b"\x1f\x8b"[..]));
+    }
+
+    #[test]
+    fn test_message_frames_line_too_long() {
+        let input = format!(
+            "<1>1 - - - - - - one\n<1>1 - - - - - - {}\n<1>1 - - - - - - two\r\n<1>1 - - - - - - four\r\n",
+            "x".repeat(100)
+        );
+        // a tiny buffer, so the long line is read in pieces
+        let reader = BufReader::with_capacity(4, input.as_bytes());
+        let mut frames = MessageFrames::new(reader).max_line_len(20);
+        assert_eq!(frames.next().unwrap().unwrap().msg, "one");
+        match frames.next() {
+            Some(Err(ReadMessageError::LineTooLong {
+                line_number,
+                max_line_len,
+            })) => {
+                assert_eq!(line_number, 2);
+                assert_eq!(max_line_len, 20);
+            }
+            other => panic!("expected LineTooLong, got {:?}", other),
+        }
+        assert!(frames.buf.capacity() < 64);
+        // the rest of the long line was skipped, and the limit doesn't count the `\r\n`
+        assert_eq!(frames.next().unwrap().unwrap().msg, "two");
+        assert_eq!(frames.line_number(), 3);
+        assert!(matches!(
+            frames.next(),
+            Some(Err(ReadMessageError::LineTooLong { line_number: 4, .. }))
+        ));
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn test_message_frames_io_error() {
+        struct Failing;
+
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("disk on fire"))
+            }
+        }
+
+        let reader = BufReader::new(&b"<1>1 - - - - - - one\n"[..]).chain(BufReader::new(Failing));
+        let mut frames = MessageFrames::new(reader);
+        assert!(frames.next().unwrap().is_ok());
+        assert!(matches!(frames.next(), Some(Err(ReadMessageError::Io(_)))));
+        assert!(frames.next().is_none());
+    }
+}
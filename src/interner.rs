@@ -0,0 +1,93 @@
+//! A small, capacity-bounded LRU string interner.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Hands out shared `Arc<str>`s for repeated strings, forgetting the least recently used one
+/// once more than `capacity` distinct strings have been seen.
+///
+/// This is intended for low-cardinality fields (hostnames, app names), so eviction is a simple
+/// linear scan rather than a linked list.
+#[derive(Debug)]
+pub(crate) struct Interner {
+    capacity: usize,
+    clock: u64,
+    entries: HashMap<Arc<str>, u64>,
+}
+
+impl Interner {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Interner {
+            capacity,
+            clock: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn intern(&mut self, s: &str) -> Arc<str> {
+        self.clock += 1;
+        if let Some((key, _)) = self.entries.get_key_value(s) {
+            let key = Arc::clone(key);
+            self.entries.insert(Arc::clone(&key), self.clock);
+            return key;
+        }
+        let value: Arc<str> = Arc::from(s);
+        if self.capacity == 0 {
+            return value;
+        }
+        if self.entries.len() >= self.capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, last_used)| **last_used)
+                .map(|(k, _)| Arc::clone(k));
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(Arc::clone(&value), self.clock);
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::Interner;
+
+    #[test]
+    fn test_shares_storage() {
+        let mut interner = Interner::new(4);
+        let a = interner.intern("host1");
+        let b = interner.intern("host1");
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(interner.len(), 1);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut interner = Interner::new(2);
+        let a = interner.intern("a");
+        interner.intern("b");
+        // touch "a" so that "b" is the oldest
+        interner.intern("a");
+        interner.intern("c");
+        assert_eq!(interner.len(), 2);
+        assert!(Arc::ptr_eq(&a, &interner.intern("a")));
+        let b = interner.intern("b");
+        assert_eq!(&*b, "b");
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let mut interner = Interner::new(0);
+        assert_eq!(&*interner.intern("a"), "a");
+        assert_eq!(interner.len(), 0);
+    }
+}
@@ -24,19 +24,60 @@
 //! }
 //! ```
 //!
-//! # Unimplemented Features
+//! # Binary Messages
 //!
-//!  * Theoretically, you can send arbitrary (non-unicode) bytes for the message part of a syslog
-//!    message. Rust doesn't have a convenient way to only treat *some* of a buffer as utf-8,
-//!    so I'm just not supporting that. Most "real" syslog servers barf on it anway.
+//! You can send arbitrary (non-unicode) bytes for the message part of a syslog message. To
+//! accept those, use `parse_message_bytes`, which keeps the original bytes in
+//! `SyslogMessage::msg_raw` alongside a lossy conversion in `msg`. Most "real" syslog servers
+//! barf on it anway.
 //!
+//! # `no_std`
+//!
+//! Without the default `std` feature, the crate is `#![no_std]` and needs only `alloc`. That
+//! leaves out `aggregate`, `framing`, `SyslogMessage::sd_to_flat`, and anything which reads the
+//! current time, and the features which need `std` (`syslog-interop`, `interning`, and
+//! `arbitrary`) turn it back on.
+//!
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod aggregate;
+#[cfg(feature = "audit")]
+pub mod audit;
+pub mod borrowed;
 mod facility;
+#[cfg(feature = "std")]
+pub mod framing;
+#[cfg(feature = "interning")]
+mod interner;
+mod logfmt;
 pub mod message;
 pub mod parser;
 mod severity;
+pub mod standard_sd;
+#[cfg(feature = "syslog-interop")]
+pub mod syslog_interop;
 
-pub use facility::SyslogFacility;
-pub use severity::SyslogSeverity;
+pub use facility::{SyslogFacility, SyslogFacilityError};
+#[cfg(feature = "log")]
+pub use severity::severity_from_log_level;
+#[cfg(feature = "tracing")]
+pub use severity::severity_from_tracing_level;
+pub use severity::{SyslogSeverity, SyslogSeverityError};
 
-pub use message::SyslogMessage;
-pub use parser::parse_message;
+pub use borrowed::SyslogMessageRef;
+pub use message::{SyslogMessage, SyslogMessageBuilder};
+#[cfg(feature = "interning")]
+pub use parser::Parser;
+pub use parser::{
+    from_priority, parse_into, parse_message, parse_message_bytes,
+    parse_message_bytes_with_options, parse_message_consumed, parse_message_lenient,
+    parse_message_ref, parse_message_ref_with_options, parse_message_strict, parse_message_with,
+    parse_message_with_options, parse_message_with_warnings, parse_messages,
+    parse_messages_with_options, parse_octet_counted, parse_octet_counted_with_options, parse_sax,
+    parse_sax_with_options, parse_structured_data_only, validate_line_report, CharClass,
+    InputEncoding, LineReport, ParseErr, ParseErrKind, ParseHandler, ParseOptions, ParseWarning,
+    Rounding, Utf8Mode,
+};
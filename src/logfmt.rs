@@ -0,0 +1,98 @@
+//! A small parser for `logfmt`-style (`key=value key2="value 2"`) message bodies.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+
+/// Parse a single key, which runs until `=`, a space, or a quote
+fn parse_key(input: &str) -> (&str, &str) {
+    let end = input
+        .find(|c: char| c == '=' || c == '"' || c.is_ascii_whitespace())
+        .unwrap_or(input.len());
+    (&input[..end], &input[end..])
+}
+
+/// Parse a double-quoted value, un-escaping backslash escapes
+fn parse_quoted(input: &str) -> Option<(String, &str)> {
+    let mut result = String::new();
+    let mut escaped = false;
+    for (idx, chr) in input.char_indices() {
+        if escaped {
+            result.push(match chr {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                c => c,
+            });
+            escaped = false;
+        } else if chr == '\\' {
+            escaped = true;
+        } else if chr == '"' {
+            return Some((result, &input[idx + 1..]));
+        } else {
+            result.push(chr);
+        }
+    }
+    None
+}
+
+/// Parse `input` as a sequence of whitespace-separated `key=value` pairs
+///
+/// Returns `None` unless the whole input consists of such pairs (with at least one of them);
+/// bare words without an `=` are rejected, so that ordinary prose isn't mistaken for logfmt.
+/// Later occurrences of a repeated key overwrite earlier ones.
+pub(crate) fn parse(input: &str) -> Option<BTreeMap<String, String>> {
+    let mut pairs = BTreeMap::new();
+    let mut rest = input.trim_start();
+    while !rest.is_empty() {
+        let (key, after_key) = parse_key(rest);
+        if key.is_empty() {
+            return None;
+        }
+        let after_eq = after_key.strip_prefix('=')?;
+        let (value, after_value) = if let Some(quoted) = after_eq.strip_prefix('"') {
+            parse_quoted(quoted)?
+        } else {
+            let (value, after_value) = parse_key(after_eq);
+            if after_value.starts_with('=') || after_value.starts_with('"') {
+                return None;
+            }
+            (String::from(value), after_value)
+        };
+        if !(after_value.is_empty() || after_value.starts_with(|c: char| c.is_ascii_whitespace())) {
+            return None;
+        }
+        pairs.insert(String::from(key), value);
+        rest = after_value.trim_start();
+    }
+    if pairs.is_empty() {
+        None
+    } else {
+        Some(pairs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn test_logfmt() {
+        let pairs = parse(r#"level=info msg="hello \"world\"" took=12ms empty= "#)
+            .expect("should parse as logfmt");
+        assert_eq!(pairs.len(), 4);
+        assert_eq!(pairs["level"], "info");
+        assert_eq!(pairs["msg"], r#"hello "world""#);
+        assert_eq!(pairs["took"], "12ms");
+        assert_eq!(pairs["empty"], "");
+    }
+
+    #[test]
+    fn test_not_logfmt() {
+        assert!(parse("").is_none());
+        assert!(parse("just some words").is_none());
+        assert!(parse("user logged in id=5").is_none());
+        assert!(parse(r#"msg="unterminated"#).is_none());
+        assert!(parse("a=b=c").is_none());
+        assert!(parse("=value").is_none());
+    }
+}
@@ -1,23 +1,46 @@
 //! In-memory representation of a single Syslog message.
 
-use std::cmp::Ordering;
-use std::collections::BTreeMap;
-use std::convert::Into;
-use std::ops;
-use std::str::FromStr;
-use std::string::String;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::convert::Into;
+#[cfg(any(feature = "std", feature = "serde-serialize"))]
+use core::convert::TryFrom;
+use core::fmt::{self, Write};
+use core::ops::{self, Bound};
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
 
+#[cfg(feature = "arbitrary")]
+use arbitrary::{Arbitrary, Unstructured};
 #[cfg(feature = "serde-serialize")]
-use serde::{Serialize, Serializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 #[allow(non_camel_case_types)]
 pub type time_t = i64;
 #[allow(non_camel_case_types)]
 pub type pid_t = i32;
+
+/// The first and last second, as Unix timestamps, of the years 0000 to 9999 that an RFC 5424
+/// FULL-DATE can express
+const MIN_TIMESTAMP: time_t = -62_167_219_200;
+const MAX_TIMESTAMP: time_t = 253_402_300_799;
 #[allow(non_camel_case_types)]
 pub type msgid_t = String;
+/// Type of the HOSTNAME and APP-NAME fields: a shared `Arc<str>` when built with the `interning`
+/// feature, so that a `Parser` can hand out the same storage for repeated values
+#[cfg(not(feature = "interning"))]
+#[allow(non_camel_case_types)]
+pub type name_t = String;
+#[cfg(feature = "interning")]
+#[allow(non_camel_case_types)]
+pub type name_t = alloc::sync::Arc<str>;
 
 use crate::facility;
+use crate::logfmt;
 use crate::parser;
 use crate::severity;
 
@@ -31,13 +54,38 @@ pub enum ProcId {
 impl PartialOrd for ProcId {
     fn partial_cmp(&self, other: &ProcId) -> Option<Ordering> {
         match (self, other) {
-            (&ProcId::PID(ref s_p), &ProcId::PID(ref o_p)) => Some(s_p.cmp(o_p)),
-            (&ProcId::Name(ref s_n), &ProcId::Name(ref o_n)) => Some(s_n.cmp(o_n)),
+            (ProcId::PID(s_p), ProcId::PID(o_p)) => Some(s_p.cmp(o_p)),
+            (ProcId::Name(s_n), ProcId::Name(o_n)) => Some(s_n.cmp(o_n)),
             _ => None,
         }
     }
 }
 
+impl fmt::Display for ProcId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProcId::PID(p) => write!(f, "{}", p),
+            ProcId::Name(n) => f.write_str(n),
+        }
+    }
+}
+
+/// Compares against the PROCID as it appeared on the wire, so `ProcId::PID(42) == "42"`
+impl PartialEq<str> for ProcId {
+    fn eq(&self, other: &str) -> bool {
+        match self {
+            ProcId::PID(p) => pid_t::from_str(other) == Ok(*p),
+            ProcId::Name(n) => n == other,
+        }
+    }
+}
+
+impl PartialEq<&str> for ProcId {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
 #[cfg(feature = "serde-serialize")]
 impl Serialize for ProcId {
     fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
@@ -48,12 +96,111 @@ impl Serialize for ProcId {
     }
 }
 
+#[cfg(feature = "serde-serialize")]
+impl<'de> Deserialize<'de> for ProcId {
+    /// Accept either form that `serialize` writes: an integer for a PID, or a string for anything
+    /// else
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        struct ProcIdVisitor;
+
+        impl de::Visitor<'_> for ProcIdVisitor {
+            type Value = ProcId;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an integer PID or a string")
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<ProcId, E> {
+                pid_t::try_from(v)
+                    .map(ProcId::PID)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &self))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<ProcId, E> {
+                pid_t::try_from(v)
+                    .map(ProcId::PID)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<ProcId, E> {
+                Ok(ProcId::Name(String::from(v)))
+            }
+        }
+
+        de.deserialize_any(ProcIdVisitor)
+    }
+}
+
+/// Generate a string of printable US-ASCII, as allowed in the header fields and SD names, that
+/// is not the NILVALUE and does not contain any of the `excluded` characters
+#[cfg(feature = "arbitrary")]
+fn arbitrary_printable(
+    u: &mut Unstructured,
+    max_len: usize,
+    excluded: &[char],
+) -> arbitrary::Result<String> {
+    let len = u.int_in_range(1..=max_len)?;
+    let mut s = String::with_capacity(len);
+    for _ in 0..len {
+        let c = char::from(u.int_in_range(b'!'..=b'~')?);
+        s.push(if excluded.contains(&c) { '_' } else { c });
+    }
+    if s == "-" {
+        s = String::from("_");
+    }
+    Ok(s)
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for ProcId {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        if u.arbitrary()? {
+            Ok(ProcId::PID(u.int_in_range(0..=pid_t::MAX)?))
+        } else {
+            // start with a letter so that the name can't be mistaken for a PID
+            let first = char::from(u.int_in_range(b'a'..=b'z')?);
+            let name = arbitrary_printable(u, 127, &[])?;
+            Ok(ProcId::Name(format!("{}{}", first, name)))
+        }
+    }
+}
+
+/// Type of SD-IDs and param names: a shared `Arc<str>` when built with the `sd-interning`
+/// feature, so that a `Parser` can hand out the same storage for repeated names
+#[cfg(not(feature = "sd-interning"))]
 pub type SDIDType = String;
-pub type SDParamIDType = String;
+#[cfg(feature = "sd-interning")]
+pub type SDIDType = alloc::sync::Arc<str>;
+pub type SDParamIDType = SDIDType;
 pub type SDParamValueType = String;
 
 pub type StructuredDataElement = BTreeMap<SDParamIDType, SDParamValueType>;
 
+/// The private enterprise number in an SD-ID of the form `name@number`
+///
+/// SD-IDs without an `@` (such as `meta` or `origin`) are reserved for names registered with
+/// IANA, and have no enterprise number. The number returned is everything after the last `@`,
+/// which may include dotted sub-identifiers chosen by the enterprise: the vendor itself is
+/// identified by the part before the first `.`. `None` is also returned if the suffix isn't
+/// such a dotted number.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::message::enterprise_number;
+///
+/// assert_eq!(enterprise_number("junos@2636.1.1.1.2.57"), Some("2636.1.1.1.2.57"));
+/// assert_eq!(enterprise_number("exampleSDID@32473"), Some("32473"));
+/// assert_eq!(enterprise_number("meta"), None);
+/// ```
+pub fn enterprise_number(sd_id: &str) -> Option<&str> {
+    let (_, number) = sd_id.rsplit_once('@')?;
+    let valid = number
+        .split('.')
+        .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()));
+    valid.then_some(number)
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 /// Container for the `StructuredData` component of a syslog message.
 ///
@@ -64,11 +211,19 @@ pub type StructuredDataElement = BTreeMap<SDParamIDType, SDParamValueType>;
 ///
 /// [foo bar="baz" bar="bing"]
 ///
-/// There's no way to retrieve the original "baz" mapping.
+/// There's no way to retrieve the original "baz" mapping, although
+/// `parse_message_with_warnings` will report that it was dropped. Parse into a
+/// `MultiStructuredData` instead to keep it.
 pub struct StructuredData {
     elements: BTreeMap<SDIDType, StructuredDataElement>,
 }
 
+impl Default for StructuredData {
+    fn default() -> Self {
+        Self::new_empty()
+    }
+}
+
 impl ops::Deref for StructuredData {
     type Target = BTreeMap<SDIDType, StructuredDataElement>;
     fn deref(&self) -> &Self::Target {
@@ -83,6 +238,32 @@ impl Serialize for StructuredData {
     }
 }
 
+#[cfg(feature = "serde-serialize")]
+impl<'de> Deserialize<'de> for StructuredData {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        Ok(StructuredData {
+            elements: BTreeMap::deserialize(de)?,
+        })
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for StructuredData {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        const SD_NAME_EXCLUDED: &[char] = &['=', ']', '"'];
+        let mut sd = StructuredData::new_empty();
+        for _ in 0..u.int_in_range(0..=4)? {
+            let sd_id = arbitrary_printable(u, 32, SD_NAME_EXCLUDED)?;
+            let element = sd.entry(sd_id);
+            for _ in 0..u.int_in_range(0..=4)? {
+                let param_id = arbitrary_printable(u, 32, SD_NAME_EXCLUDED)?;
+                element.insert(Into::into(param_id), u.arbitrary()?);
+            }
+        }
+        Ok(sd)
+    }
+}
+
 impl StructuredData {
     pub fn new_empty() -> Self {
         StructuredData {
@@ -91,13 +272,11 @@ impl StructuredData {
     }
 
     /// Fetch or insert a new sd_id entry into the StructuredData
-    pub fn entry<SI>(&mut self, sd_id: SI) -> &mut BTreeMap<String, String>
+    pub fn entry<SI>(&mut self, sd_id: SI) -> &mut StructuredDataElement
     where
         SI: Into<SDIDType>,
     {
-        self.elements
-            .entry(sd_id.into())
-            .or_insert_with(BTreeMap::new)
+        self.elements.entry(sd_id.into()).or_default()
     }
 
     /// Insert a new (sd_id, sd_param_id) -> sd_value mapping into the StructuredData
@@ -129,11 +308,259 @@ impl StructuredData {
         }
     }
 
+    /// Lookup by SDID, SDParamID pair, ignoring ASCII case in both
+    ///
+    /// An exact match is preferred; otherwise this has to scan every element (and then every
+    /// param of the matching elements), so it is O(n) where `find_tuple` is O(log n). If several
+    /// spellings match, the first in sorted order wins.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use syslog_rfc5424::parse_message;
+    ///
+    /// let msg = parse_message(r#"<1>1 - - - - - [Meta SequenceID="29"] -"#).unwrap();
+    ///
+    /// assert_eq!(msg.sd.find_tuple("meta", "sequenceId"), None);
+    /// assert_eq!(msg.sd.find_tuple_ignore_case("meta", "sequenceId").unwrap(), "29");
+    /// ```
+    pub fn find_tuple_ignore_case<'b>(
+        &'b self,
+        sd_id: &str,
+        sd_param_id: &str,
+    ) -> Option<&'b SDParamValueType> {
+        if let Some(value) = self.find_tuple(sd_id, sd_param_id) {
+            return Some(value);
+        }
+        self.elements
+            .iter()
+            .filter(|(id, _)| id.eq_ignore_ascii_case(sd_id))
+            .flat_map(|(_, element)| element.iter())
+            .find(|(param_id, _)| param_id.eq_ignore_ascii_case(sd_param_id))
+            .map(|(_, value)| value)
+    }
+
+    /// Lookup by SDID, SDParamID pair, parsing the (already unescaped) value as JSON
+    ///
+    /// Returns `None` if the param is missing or its value isn't valid JSON.
+    #[cfg(feature = "serde-serialize")]
+    pub fn find_tuple_json(&self, sd_id: &str, sd_param_id: &str) -> Option<serde_json::Value> {
+        serde_json::from_str(self.find_tuple(sd_id, sd_param_id)?).ok()
+    }
+
+    /// Whether there is an element with the given SDID
+    pub fn contains_sdid(&self, sd_id: &str) -> bool {
+        self.elements.contains_key(sd_id)
+    }
+
+    /// Whether the given SDID has a value for the given SDParamID
+    pub fn contains_tuple(&self, sd_id: &str, sd_param_id: &str) -> bool {
+        self.find_tuple(sd_id, sd_param_id).is_some()
+    }
+
+    /// A handle for setting and removing the params of one SDID, in the style of
+    /// `btree_map::Entry`
+    ///
+    /// The element is only created once a param is set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use syslog_rfc5424::message::StructuredData;
+    ///
+    /// let mut sd = StructuredData::new_empty();
+    /// sd.sd_entry("origin")
+    ///     .set("ip", "192.0.2.1")
+    ///     .set("software", "example")
+    ///     .remove("ip");
+    ///
+    /// assert_eq!(sd.find_tuple("origin", "software").map(String::as_str), Some("example"));
+    /// assert!(!sd.contains_tuple("origin", "ip"));
+    /// ```
+    pub fn sd_entry<SI>(&mut self, sd_id: SI) -> SdEntry<'_>
+    where
+        SI: Into<SDIDType>,
+    {
+        SdEntry {
+            elements: &mut self.elements,
+            sd_id: sd_id.into(),
+        }
+    }
+
     /// Find all param/value mappings for a given SDID
     pub fn find_sdid<'b>(&'b self, sd_id: &str) -> Option<&'b StructuredDataElement> {
         self.elements.get(sd_id)
     }
 
+    /// All params of the given SDID whose names start with `prefix`, in sorted order
+    ///
+    /// This is handy for vendor extension conventions, where the exact param names aren't known
+    /// up front. The iterator is empty if the SDID isn't present.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use syslog_rfc5424::parse_message;
+    ///
+    /// let msg = parse_message(
+    ///     r#"<1>1 - - - - - [origin ip="192.0.2.1" x-rack="r7" x-zone="b"] -"#,
+    /// ).unwrap();
+    /// let extensions: Vec<_> = msg.sd.find_params_by_prefix("origin", "x-").collect();
+    ///
+    /// assert_eq!(extensions, vec![("x-rack", "r7"), ("x-zone", "b")]);
+    /// ```
+    pub fn find_params_by_prefix<'b>(
+        &'b self,
+        sd_id: &str,
+        prefix: &'b str,
+    ) -> impl Iterator<Item = (&'b str, &'b str)> + 'b {
+        self.elements
+            .get(sd_id)
+            .into_iter()
+            .flat_map(move |element| {
+                element
+                    .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+                    .take_while(move |(k, _)| k.starts_with(prefix))
+            })
+            .map(|(k, v)| (&**k, v.as_str()))
+    }
+
+    /// The number of distinct SD_IDs
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Whether or not this is empty
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Remove every element
+    pub fn clear(&mut self) {
+        self.elements.clear()
+    }
+}
+
+/// A container the parser can collect structured data into; see `parser::parse_message_with`
+pub trait StructuredDataMap: Default {
+    /// The params of one element, by name
+    type Element;
+
+    /// Record that an element with this SDID was present, even if it has no params
+    fn add_element(&mut self, sd_id: &str);
+
+    /// `add_element` for an SDID the caller already has as an `SDIDType` (such as a name shared
+    /// through a `Parser`), which containers may keep rather than copying
+    fn add_element_owned(&mut self, sd_id: SDIDType) {
+        self.add_element(&sd_id)
+    }
+
+    /// Whether the element with this SDID already has a value for the param
+    fn contains_param(&self, sd_id: &str, sd_param_id: &str) -> bool;
+
+    /// Record one param of an element
+    fn add_param(
+        &mut self,
+        sd_id: &str,
+        sd_param_id: SDParamIDType,
+        sd_param_value: SDParamValueType,
+    );
+
+    /// Iterate over the elements, sorted by SDID
+    ///
+    /// This is for code which is generic over the container; it is the same as the `iter` that
+    /// each container has of its own.
+    fn iter_elements(&self) -> impl Iterator<Item = (&str, &Self::Element)>;
+}
+
+/// Keeps only the last value of a repeated param
+impl StructuredDataMap for StructuredData {
+    type Element = StructuredDataElement;
+
+    fn add_element(&mut self, sd_id: &str) {
+        if !self.elements.contains_key(sd_id) {
+            self.elements.insert(SDIDType::from(sd_id), BTreeMap::new());
+        }
+    }
+
+    fn add_element_owned(&mut self, sd_id: SDIDType) {
+        self.elements.entry(sd_id).or_default();
+    }
+
+    fn contains_param(&self, sd_id: &str, sd_param_id: &str) -> bool {
+        self.elements
+            .get(sd_id)
+            .is_some_and(|element| element.contains_key(sd_param_id))
+    }
+
+    fn add_param(
+        &mut self,
+        sd_id: &str,
+        sd_param_id: SDParamIDType,
+        sd_param_value: SDParamValueType,
+    ) {
+        self.add_element(sd_id);
+        self.elements
+            .get_mut(sd_id)
+            .expect("element was just added")
+            .insert(sd_param_id, sd_param_value);
+    }
+
+    fn iter_elements(&self) -> impl Iterator<Item = (&str, &Self::Element)> {
+        self.elements
+            .iter()
+            .map(|(sd_id, element)| (&**sd_id, element))
+    }
+}
+
+/// Structured data which keeps every value of a repeated param, for when nothing may be lost
+///
+/// Use it by parsing with `parser::parse_message_with::<MultiStructuredData>`. Params are still
+/// grouped by SDID and sorted by name, but each one holds all of its values in the order they
+/// appeared.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::message::MultiStructuredData;
+/// use syslog_rfc5424::{parse_message_with, ParseOptions};
+///
+/// let msg = parse_message_with::<MultiStructuredData>(
+///     r#"<1>1 - - - - - [foo bar="baz" bar="bing"][foo bar="bop"] -"#,
+///     &ParseOptions::default(),
+/// ).unwrap();
+///
+/// assert_eq!(msg.sd.find_all_tuples("foo", "bar"), ["baz", "bing", "bop"]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MultiStructuredData {
+    elements: BTreeMap<SDIDType, BTreeMap<SDParamIDType, Vec<SDParamValueType>>>,
+}
+
+impl MultiStructuredData {
+    /// Every value of a param, in the order they appeared; empty if there were none
+    pub fn find_all_tuples(&self, sd_id: &str, sd_param_id: &str) -> &[SDParamValueType] {
+        self.elements
+            .get(sd_id)
+            .and_then(|element| element.get(sd_param_id))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// All the params of the given SDID, if present
+    pub fn find_sdid(
+        &self,
+        sd_id: &str,
+    ) -> Option<&BTreeMap<SDParamIDType, Vec<SDParamValueType>>> {
+        self.elements.get(sd_id)
+    }
+
+    /// Iterate over the elements, sorted by SDID
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (&SDIDType, &BTreeMap<SDParamIDType, Vec<SDParamValueType>>)> {
+        self.elements.iter()
+    }
+
     /// The number of distinct SD_IDs
     pub fn len(&self) -> usize {
         self.elements.len()
@@ -145,62 +572,984 @@ impl StructuredData {
     }
 }
 
-#[cfg_attr(feature = "serde-serialize", derive(Serialize))]
+impl StructuredDataMap for MultiStructuredData {
+    type Element = BTreeMap<SDParamIDType, Vec<SDParamValueType>>;
+
+    fn add_element(&mut self, sd_id: &str) {
+        if !self.elements.contains_key(sd_id) {
+            self.elements.insert(SDIDType::from(sd_id), BTreeMap::new());
+        }
+    }
+
+    fn add_element_owned(&mut self, sd_id: SDIDType) {
+        self.elements.entry(sd_id).or_default();
+    }
+
+    fn contains_param(&self, sd_id: &str, sd_param_id: &str) -> bool {
+        self.elements
+            .get(sd_id)
+            .is_some_and(|element| element.contains_key(sd_param_id))
+    }
+
+    fn add_param(
+        &mut self,
+        sd_id: &str,
+        sd_param_id: SDParamIDType,
+        sd_param_value: SDParamValueType,
+    ) {
+        self.add_element(sd_id);
+        self.elements
+            .get_mut(sd_id)
+            .expect("element was just added")
+            .entry(sd_param_id)
+            .or_default()
+            .push(sd_param_value);
+    }
+
+    fn iter_elements(&self) -> impl Iterator<Item = (&str, &Self::Element)> {
+        self.elements
+            .iter()
+            .map(|(sd_id, element)| (&**sd_id, element))
+    }
+}
+
+#[cfg(feature = "serde-serialize")]
+impl Serialize for MultiStructuredData {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        self.elements.serialize(ser)
+    }
+}
+
+#[cfg(feature = "serde-serialize")]
+impl<'de> Deserialize<'de> for MultiStructuredData {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        Ok(MultiStructuredData {
+            elements: BTreeMap::deserialize(de)?,
+        })
+    }
+}
+
+impl fmt::Display for StructuredData {
+    /// Render the structured data in wire format, re-escaping `"`, `\`, and `]` in param values.
+    /// Empty structured data is rendered as `-`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("-");
+        }
+        for (sd_id, params) in &self.elements {
+            write!(f, "[{}", sd_id)?;
+            for (name, value) in params {
+                write!(f, " {}=\"", name)?;
+                for c in value.chars() {
+                    if matches!(c, '"' | '\\' | ']') {
+                        f.write_char('\\')?;
+                    }
+                    f.write_char(c)?;
+                }
+                f.write_char('"')?;
+            }
+            f.write_char(']')?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes a message's structured data in wire format, returned by `SyslogMessage::sd_display`
+///
+/// This renders exactly as `StructuredData`'s own `Display` impl does, straight into the
+/// formatter.
+#[derive(Clone, Copy, Debug)]
+pub struct StructuredDataDisplay<'a>(&'a StructuredData);
+
+impl fmt::Display for StructuredDataDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.0, f)
+    }
+}
+
+/// A mutable handle on the params of one SDID, returned by `StructuredData::sd_entry`
+#[derive(Debug)]
+pub struct SdEntry<'a> {
+    elements: &'a mut BTreeMap<SDIDType, StructuredDataElement>,
+    sd_id: SDIDType,
+}
+
+impl<'a> SdEntry<'a> {
+    /// The SDID this entry refers to
+    pub fn sd_id(&self) -> &str {
+        &self.sd_id
+    }
+
+    /// The current value of a param, if set
+    pub fn get(&self, sd_param_id: &str) -> Option<&SDParamValueType> {
+        self.elements.get(&self.sd_id)?.get(sd_param_id)
+    }
+
+    /// Set a param, creating the element if it doesn't exist yet
+    pub fn set<SPI, SPV>(&mut self, sd_param_id: SPI, sd_param_value: SPV) -> &mut Self
+    where
+        SPI: Into<SDParamIDType>,
+        SPV: Into<SDParamValueType>,
+    {
+        self.elements
+            .entry(self.sd_id.clone())
+            .or_default()
+            .insert(sd_param_id.into(), sd_param_value.into());
+        self
+    }
+
+    /// Remove a param, if set. The element itself is kept, even if it has no params left.
+    pub fn remove(&mut self, sd_param_id: &str) -> &mut Self {
+        if let Some(element) = self.elements.get_mut(&self.sd_id) {
+            element.remove(sd_param_id);
+        }
+        self
+    }
+
+    /// Remove the whole element, returning its params if it existed
+    pub fn remove_entry(self) -> Option<StructuredDataElement> {
+        self.elements.remove(&self.sd_id)
+    }
+
+    /// Get the element's params, creating an empty element if it doesn't exist yet
+    pub fn or_default(self) -> &'a mut StructuredDataElement {
+        self.elements.entry(self.sd_id).or_default()
+    }
+}
+
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug, PartialEq, Eq)]
 /// A RFC5424-protocol syslog message
-pub struct SyslogMessage {
+///
+/// The structured data is normally a `StructuredData`; see `parser::parse_message_with` for
+/// parsing it into something else.
+pub struct SyslogMessage<SD = StructuredData> {
     pub severity: severity::SyslogSeverity,
     pub facility: facility::SyslogFacility,
     pub version: i32,
     pub timestamp: Option<time_t>,
     pub timestamp_nanos: Option<u32>,
-    pub hostname: Option<String>,
-    pub appname: Option<String>,
+    /// Whether the timestamp's offset was `-00:00`, which RFC 3339 uses to mean that the time is
+    /// in UTC but the sender's local offset is unknown
+    pub timestamp_offset_unknown: bool,
+    /// The UTC offset the timestamp was given in, in seconds east of UTC (`Z` is `Some(0)`).
+    /// `timestamp` is always relative to the Unix epoch regardless, so this only records where
+    /// the message came from.
+    pub timestamp_offset_secs: Option<i32>,
+    pub hostname: Option<name_t>,
+    pub appname: Option<name_t>,
     pub procid: Option<ProcId>,
     pub msgid: Option<msgid_t>,
-    pub sd: StructuredData,
+    pub sd: SD,
     pub msg: String,
+    /// Whether the MSG started with a byte order mark, which RFC 5424 uses to declare that it is
+    /// UTF-8. The BOM itself is not kept in `msg`.
+    pub msg_has_bom: bool,
+    /// The exact bytes of the MSG, when they aren't valid UTF-8 and `msg` only holds a lossy
+    /// conversion of them. Only `parser::parse_message_bytes` sets this.
+    pub msg_raw: Option<Vec<u8>>,
+    /// Transport metadata split off the end of the MSG (see
+    /// `ParseOptions::trailing_field_delimiter`)
+    pub trailer: Option<String>,
 }
 
-impl FromStr for SyslogMessage {
-    type Err = parser::ParseErr;
-
-    /// Parse a string into a `SyslogMessage`
-    ///
-    /// Just calls `parser::parse_message`
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        parser::parse_message(s)
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for SyslogMessage {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        // between 0000-01-01 and 9999-12-31, at the microsecond resolution RFC 5424 allows
+        let timestamp = if u.arbitrary()? {
+            Some(u.int_in_range(MIN_TIMESTAMP..=MAX_TIMESTAMP)?)
+        } else {
+            None
+        };
+        fn optional_term<T: From<String>>(
+            u: &mut Unstructured,
+            max_len: usize,
+        ) -> arbitrary::Result<Option<T>> {
+            if u.arbitrary()? {
+                Ok(Some(arbitrary_printable(u, max_len, &[])?.into()))
+            } else {
+                Ok(None)
+            }
+        }
+        Ok(SyslogMessage {
+            severity: u.arbitrary()?,
+            facility: u.arbitrary()?,
+            version: 1,
+            timestamp,
+            timestamp_nanos: match timestamp {
+                Some(_) => Some(u.int_in_range(0..=999_999)? * 1000),
+                None => None,
+            },
+            timestamp_offset_unknown: false,
+            timestamp_offset_secs: timestamp.map(|_| 0),
+            hostname: optional_term(u, 255)?,
+            appname: optional_term(u, 48)?,
+            procid: u.arbitrary()?,
+            msgid: optional_term(u, 32)?,
+            sd: u.arbitrary()?,
+            msg: u.arbitrary()?,
+            msg_has_bom: u.arbitrary()?,
+            msg_raw: None,
+            trailer: None,
+        })
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::StructuredData;
-    use super::SyslogMessage;
-    #[cfg(feature = "serde-serialize")]
-    use crate::facility::SyslogFacility::*;
-    #[cfg(feature = "serde-serialize")]
-    use crate::severity::SyslogSeverity::*;
-    #[cfg(feature = "serde-serialize")]
-    use serde_json;
+/// 64-bit FNV-1a; used for fingerprints because, unlike `DefaultHasher`, its output is
+/// specified and will never change between Rust releases.
+struct Fnv1a(u64);
 
-    #[test]
-    fn test_structured_data_basic() {
-        let mut s = StructuredData::new_empty();
-        s.insert_tuple("foo", "bar", "baz");
-        let v = s.find_tuple("foo", "bar").expect("should find foo/bar");
-        assert_eq!(v, "baz");
-        assert!(s.find_tuple("foo", "baz").is_none());
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
     }
 
-    #[cfg(feature = "serde-serialize")]
-    #[test]
-    fn test_structured_data_serialization_serde() {
-        let mut s = StructuredData::new_empty();
-        s.insert_tuple("foo", "bar", "baz");
-        s.insert_tuple("foo", "baz", "bar");
-        s.insert_tuple("faa", "bar", "baz");
-        let encoded = serde_json::to_string(&s).expect("Should encode to JSON");
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= u64::from(*b);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn write_str(&mut self, s: &str) {
+        self.write(&(s.len() as u64).to_le_bytes());
+        self.write(s.as_bytes());
+    }
+
+    fn write_opt_str(&mut self, s: Option<&str>) {
+        match s {
+            None => self.write(&[0]),
+            Some(s) => {
+                self.write(&[1]);
+                self.write_str(s);
+            }
+        }
+    }
+}
+
+impl SyslogMessage {
+    /// Set the timestamp to `secs` seconds and `nanos` nanoseconds after the Unix epoch
+    ///
+    /// `nanos` values of a second or more carry over into `secs`. The offset is set to UTC.
+    ///
+    /// # Panics
+    ///
+    /// If the result is outside 0000-01-01T00:00:00Z to 9999-12-31T23:59:59Z (`secs` from
+    /// -62167219200 to 253402300799), which RFC 5424 has no way to write.
+    pub fn with_timestamp(mut self, secs: time_t, nanos: u32) -> Self {
+        let secs = secs
+            .checked_add(time_t::from(nanos / 1_000_000_000))
+            .filter(|secs| (MIN_TIMESTAMP..=MAX_TIMESTAMP).contains(secs))
+            .expect("timestamp outside the years 0000 to 9999");
+        self.timestamp = Some(secs);
+        self.timestamp_nanos = Some(nanos % 1_000_000_000);
+        self.timestamp_offset_unknown = false;
+        self.timestamp_offset_secs = Some(0);
+        self
+    }
+
+    /// Set the timestamp to the current time, according to the system clock
+    #[cfg(feature = "std")]
+    pub fn with_timestamp_now(self) -> Self {
+        let now = time::OffsetDateTime::now_utc();
+        self.with_timestamp(now.unix_timestamp(), now.nanosecond())
+    }
+
+    /// The timestamp as a `(seconds, nanoseconds)` pair since the Unix epoch
+    ///
+    /// This is the replacement for the `time::Timespec` that older versions of this crate used
+    /// internally: `timestamp` and `timestamp_nanos` are the two halves of the same value, and
+    /// this keeps them together. A missing `timestamp_nanos` is treated as zero.
+    pub fn timestamp_timespec(&self) -> Option<(time_t, u32)> {
+        Some((self.timestamp?, self.timestamp_nanos.unwrap_or(0)))
+    }
+
+    /// The timestamp as a `time::OffsetDateTime` in the offset the sender gave it in
+    ///
+    /// This combines `timestamp`, `timestamp_nanos`, and `timestamp_offset_secs` (a missing
+    /// offset is taken as UTC). Returns `None` if the message has no timestamp.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use syslog_rfc5424::parse_message;
+    ///
+    /// let msg = parse_message("<1>1 2017-07-26T14:47:35.869952+05:30 - - - - - -").unwrap();
+    /// let datetime = msg.offset_datetime().unwrap();
+    ///
+    /// assert_eq!((datetime.hour(), datetime.minute()), (14, 47));
+    /// assert_eq!(datetime.offset().whole_minutes(), 330);
+    /// ```
+    pub fn offset_datetime(&self) -> Option<time::OffsetDateTime> {
+        let offset =
+            time::UtcOffset::from_whole_seconds(self.timestamp_offset_secs.unwrap_or(0)).ok()?;
+        self.timestamp_in_offset(offset)
+    }
+
+    /// The timestamp as a `chrono::DateTime` in UTC
+    ///
+    /// Returns `None` if the message has no timestamp.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use syslog_rfc5424::parse_message;
+    ///
+    /// let msg = parse_message("<1>1 1985-04-12T19:20:50.52-04:00 - - - - - -").unwrap();
+    /// let datetime = msg.datetime().unwrap();
+    ///
+    /// assert_eq!(datetime.timestamp(), 482196050);
+    /// assert_eq!(datetime.timestamp_subsec_millis(), 520);
+    /// ```
+    #[cfg(feature = "chrono")]
+    pub fn datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.timestamp_in_timezone(&chrono::Utc)
+    }
+
+    /// The timestamp as a `chrono::DateTime` in the offset the sender gave it in; see
+    /// `offset_datetime`
+    #[cfg(feature = "chrono")]
+    pub fn chrono_datetime(&self) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+        let offset = chrono::FixedOffset::east_opt(self.timestamp_offset_secs.unwrap_or(0))?;
+        self.timestamp_in_timezone(&offset)
+    }
+
+    /// The timestamp as seen from the given UTC offset, for display in the reader's time zone
+    /// rather than the sender's
+    ///
+    /// The instant is the same as `timestamp`; only the offset it's expressed in changes. Returns
+    /// `None` if the message has no timestamp or it can't be represented in that offset.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use syslog_rfc5424::parse_message;
+    /// use time::UtcOffset;
+    ///
+    /// let msg = parse_message("<1>1 2016-01-15T00:04:01Z host - - - - hi").unwrap();
+    /// let local = msg
+    ///     .timestamp_in_offset(UtcOffset::from_hms(-8, 0, 0).unwrap())
+    ///     .unwrap();
+    ///
+    /// assert_eq!((local.day(), local.hour()), (14, 16));
+    /// ```
+    pub fn timestamp_in_offset(&self, offset: time::UtcOffset) -> Option<time::OffsetDateTime> {
+        let (secs, nanos) = self.timestamp_timespec()?;
+        let utc = time::OffsetDateTime::from_unix_timestamp(secs)
+            .ok()?
+            .replace_nanosecond(nanos)
+            .ok()?;
+        utc.checked_to_offset(offset)
+    }
+
+    /// The timestamp as a `chrono::DateTime` in the given time zone, for display
+    ///
+    /// This is `timestamp_in_offset` for `chrono` users; `tz` may be any `chrono::TimeZone`, such
+    /// as a `FixedOffset` or a `chrono_tz::Tz`.
+    #[cfg(feature = "chrono")]
+    pub fn timestamp_in_timezone<Tz: chrono::TimeZone>(
+        &self,
+        tz: &Tz,
+    ) -> Option<chrono::DateTime<Tz>> {
+        let (secs, nanos) = self.timestamp_timespec()?;
+        Some(chrono::DateTime::from_timestamp(secs, nanos)?.with_timezone(tz))
+    }
+
+    /// How long ago the message's timestamp was, according to the system clock
+    ///
+    /// Timestamps in the future give an age of zero. Returns `None` if the message has no
+    /// timestamp.
+    #[cfg(feature = "std")]
+    pub fn age(&self) -> Option<core::time::Duration> {
+        let timestamp = time::OffsetDateTime::from_unix_timestamp(self.timestamp?).ok()?
+            + time::Duration::nanoseconds(i64::from(self.timestamp_nanos.unwrap_or(0)));
+        let age = time::OffsetDateTime::now_utc() - timestamp;
+        Some(core::time::Duration::try_from(age).unwrap_or_default())
+    }
+
+    /// Format the structured data in wire format (`-` if there is none) without building an
+    /// intermediate `String`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use syslog_rfc5424::parse_message;
+    ///
+    /// let msg = parse_message(r#"<1>1 - - - - - [meta sequenceId="29"] hi"#).unwrap();
+    ///
+    /// assert_eq!(format!("sd={}", msg.sd_display()), r#"sd=[meta sequenceId="29"]"#);
+    /// ```
+    pub fn sd_display(&self) -> StructuredDataDisplay<'_> {
+        StructuredDataDisplay(&self.sd)
+    }
+
+    /// The PRI value encoding the message's facility and severity (`facility * 8 + severity`)
+    pub fn priority(&self) -> i32 {
+        self.facility.as_int() << 3 | self.severity.as_int()
+    }
+
+    /// Reset the message to an empty state, keeping the allocation of `msg`
+    ///
+    /// Every optional field becomes `None` and `msg` and `sd` are emptied. Severity, facility, and
+    /// version have no empty value, so they're left as they are. See `parser::parse_into` for
+    /// refilling a cleared message.
+    ///
+    /// Only `msg` keeps its capacity: the other strings are optional, so clearing them drops them,
+    /// and the `BTreeMap` behind `sd` frees its nodes as they're removed.
+    pub fn clear(&mut self) {
+        self.timestamp = None;
+        self.timestamp_nanos = None;
+        self.timestamp_offset_unknown = false;
+        self.timestamp_offset_secs = None;
+        self.hostname = None;
+        self.appname = None;
+        self.procid = None;
+        self.msgid = None;
+        self.sd.clear();
+        self.msg.clear();
+        self.msg_has_bom = false;
+        self.msg_raw = None;
+        self.trailer = None;
+    }
+
+    /// Compute a stable fingerprint of the "shape" of this message, e.g. for deduplicating alerts
+    ///
+    /// The fingerprint is a 64-bit FNV-1a hash over, in order:
+    ///
+    ///  * the facility and severity, as their wire integers
+    ///  * the HOSTNAME, APP-NAME, and MSGID (an absent field hashes differently from an empty one)
+    ///  * each SD-ID in sorted order, followed by its param names in sorted order
+    ///
+    /// Every string is hashed with its length as a prefix, so field boundaries are unambiguous.
+    /// The timestamp, PROCID, SD param values, and MSG body are deliberately left out, since
+    /// they vary between occurrences of the same event. The algorithm is fixed, so fingerprints
+    /// may be stored and compared across runs and versions of this crate.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fnv1a::new();
+        hasher.write(&[self.facility as u8, self.severity as u8]);
+        hasher.write_opt_str(self.hostname.as_deref());
+        hasher.write_opt_str(self.appname.as_deref());
+        hasher.write_opt_str(self.msgid.as_deref());
+        hasher.write(&(self.sd.len() as u64).to_le_bytes());
+        for (sd_id, params) in self.sd.iter() {
+            hasher.write_str(sd_id);
+            hasher.write(&(params.len() as u64).to_le_bytes());
+            for param_id in params.keys() {
+                hasher.write_str(param_id);
+            }
+        }
+        hasher.0
+    }
+
+    /// Rebuild the structured data by passing each `(sd_id, param_id, value)` triple through `f`
+    ///
+    /// Triples for which `f` returns `None` are dropped; otherwise the returned triple replaces
+    /// the original, so `f` may rename SD-IDs and params as well as rewrite values. SD-IDs which
+    /// have no params at all are kept as they are.
+    pub fn map_structured_data<F>(mut self, mut f: F) -> Self
+    where
+        F: FnMut(&str, &str, &str) -> Option<(String, String, String)>,
+    {
+        let mut sd = StructuredData::new_empty();
+        for (sd_id, params) in self.sd.elements.iter() {
+            if params.is_empty() {
+                sd.entry(&**sd_id);
+            }
+            for (param_id, value) in params.iter() {
+                if let Some((new_id, new_param_id, new_value)) = f(sd_id, param_id, value) {
+                    sd.insert_tuple(new_id, new_param_id, new_value);
+                }
+            }
+        }
+        self.sd = sd;
+        self
+    }
+
+    /// Flatten the structured data into a single map keyed on `(sd_id, param_id)`
+    ///
+    /// SD-IDs which have no params do not appear in the result.
+    #[cfg(feature = "std")]
+    pub fn sd_to_flat(&self) -> HashMap<(SDIDType, SDParamIDType), SDParamValueType> {
+        self.sd
+            .iter()
+            .flat_map(|(sd_id, params)| {
+                params.iter().map(move |(param_id, value)| {
+                    ((sd_id.clone(), param_id.clone()), value.clone())
+                })
+            })
+            .collect()
+    }
+
+    /// Try to parse the message body as `logfmt`-style `key=value key2="value 2"` pairs
+    ///
+    /// Returns `None` unless the whole body consists of such pairs. Quoted values may contain
+    /// backslash escapes; a repeated key keeps its last value.
+    pub fn parse_msg_logfmt(&self) -> Option<BTreeMap<String, String>> {
+        logfmt::parse(&self.msg)
+    }
+
+    /// The JSON following a CEE `@cee:` cookie at the start of the message body, as written by
+    /// rsyslog and friends
+    ///
+    /// Whitespace between the cookie and the JSON is skipped. The payload isn't validated; see
+    /// `cee_json` for that. Returns `None` if the body doesn't start with the cookie.
+    pub fn cee_payload(&self) -> Option<&str> {
+        let body = self.msg.strip_prefix('\u{feff}').unwrap_or(&self.msg);
+        Some(body.strip_prefix("@cee:")?.trim_start())
+    }
+
+    /// The CEE payload (see `cee_payload`), parsed as JSON
+    ///
+    /// Returns `None` if the cookie is absent or the payload isn't valid JSON.
+    #[cfg(feature = "serde-serialize")]
+    pub fn cee_json(&self) -> Option<serde_json::Value> {
+        serde_json::from_str(self.cee_payload()?).ok()
+    }
+
+    /// Try to parse the message body as a Linux audit record
+    /// (`type=SYSCALL msg=audit(1364481363.243:24287): key=val ...`)
+    ///
+    /// Returns `None` if the body isn't shaped like one.
+    #[cfg(feature = "audit")]
+    pub fn parse_msg_audit(&self) -> Option<crate::audit::AuditFields> {
+        crate::audit::parse(&self.msg)
+    }
+
+    /// Replace the message body with the result of applying `f` to it, e.g. to scrub PII
+    ///
+    /// ```
+    /// use syslog_rfc5424::SyslogMessage;
+    ///
+    /// let mut msg = "<1>1 - - - - - - login from alice@example.com"
+    ///     .parse::<SyslogMessage>()
+    ///     .unwrap();
+    /// msg.redact_msg(|body| {
+    ///     body.split(' ')
+    ///         .map(|word| if word.contains('@') { "[email]" } else { word })
+    ///         .collect::<Vec<_>>()
+    ///         .join(" ")
+    /// });
+    /// assert_eq!(msg.msg, "login from [email]");
+    /// ```
+    pub fn redact_msg<F: Fn(&str) -> String>(&mut self, f: F) {
+        self.msg = f(&self.msg);
+    }
+
+    /// Remove a `host: ` prefix added to the message body by a relay, returning the host
+    ///
+    /// Only strips a prefix which is a valid DNS hostname (dot-separated labels of ASCII
+    /// letters, digits, and inner hyphens) followed by `": "`; otherwise the message is left
+    /// untouched and `None` is returned. Note that an ordinary message such as `"Error: disk
+    /// full"` has exactly this shape, so only call this on traffic known to come through such a
+    /// relay.
+    pub fn strip_relay_prefix(&mut self) -> Option<String> {
+        let idx = self.msg.find(": ")?;
+        let host = &self.msg[..idx];
+        if !is_hostname(host) {
+            return None;
+        }
+        let host = String::from(host);
+        self.msg.drain(..idx + 2);
+        Some(host)
+    }
+}
+
+fn is_hostname(s: &str) -> bool {
+    !s.is_empty()
+        && s.len() <= 255
+        && s.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        })
+}
+
+impl SyslogMessage {
+    /// Render the message in RFC 5424 wire format; the same as `to_string()`
+    ///
+    /// See the `Display` impl for details.
+    pub fn to_rfc5424_string(&self) -> String {
+        self.to_string()
+    }
+
+    /// A one-line summary in the style of a classic syslog file, such as
+    /// `[cron.info] host1 CROND[10391]: some_message`
+    ///
+    /// This is meant for people to read, not for parsing back: the timestamp, MSGID, and
+    /// structured data are left out, and a missing hostname or app name is shown as `-`. Use
+    /// `to_rfc5424_string` for the whole message.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use syslog_rfc5424::parse_message;
+    ///
+    /// let msg = parse_message(r#"<78>1 2016-01-15T00:04:01Z host1 CROND 10391 - [meta sequenceId="29"] some_message"#).unwrap();
+    ///
+    /// assert_eq!(msg.summary(), "[cron.info] host1 CROND[10391]: some_message");
+    /// ```
+    pub fn summary(&self) -> String {
+        let mut out = format!(
+            "[{}.{}] {} {}",
+            self.facility.as_str(),
+            self.severity.as_str(),
+            self.hostname.as_deref().unwrap_or("-"),
+            self.appname.as_deref().unwrap_or("-"),
+        );
+        if let Some(procid) = &self.procid {
+            // writing to a String can't fail
+            let _ = write!(out, "[{}]", procid);
+        }
+        out.push(':');
+        if !self.msg.is_empty() {
+            out.push(' ');
+            out.push_str(&self.msg);
+        }
+        out
+    }
+
+    /// The structured data in a canonical RFC 5424 encoding, for use as the input to a signature
+    /// or MAC over just the SD
+    ///
+    /// Two messages whose structured data holds the same values produce the same bytes, however
+    /// it was laid out on the wire:
+    ///
+    ///  * SD elements are sorted by SD-ID, and params within an element by name, comparing bytes
+    ///  * an SD-ID which appeared more than once is written as a single element holding the
+    ///    union of its params, and a repeated param keeps only its last value (as when parsing)
+    ///  * param values are quoted, with `"`, `\`, and `]` (and only those) escaped by a `\`
+    ///  * there is no whitespace other than a single space before each param
+    ///  * empty structured data is the single byte `-`
+    ///
+    /// The result is UTF-8 with no BOM or normalization applied.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use syslog_rfc5424::parse_message;
+    ///
+    /// let a = parse_message(r#"<1>1 - - - - - [b y="2" x="1"][a z="\]"] -"#).unwrap();
+    /// let b = parse_message(r#"<1>1 - - - - - [a z="]"][b x="1"][b y="2"] -"#).unwrap();
+    ///
+    /// assert_eq!(a.structured_data_canonical_bytes(), br#"[a z="\]"][b x="1" y="2"]"#);
+    /// assert_eq!(a.structured_data_canonical_bytes(), b.structured_data_canonical_bytes());
+    /// ```
+    pub fn structured_data_canonical_bytes(&self) -> Vec<u8> {
+        // `StructuredData` is kept sorted, so its wire rendering is already canonical
+        self.sd.to_string().into_bytes()
+    }
+
+    fn fmt_timestamp(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let offset_secs = self.timestamp_offset_secs.unwrap_or(0);
+        // shift to the sender's wall clock time, then print the offset alongside it
+        let local = self.timestamp.and_then(|ts| {
+            time::OffsetDateTime::from_unix_timestamp(ts.checked_add(time_t::from(offset_secs))?)
+                .ok()
+        });
+        // a FULL-DATE has a four digit year; `with_timestamp` rejects anything else, so this is
+        // only reached through the public fields
+        let local = match local {
+            Some(local) if (0..=9999).contains(&local.year()) => local,
+            _ => return f.write_str("-"),
+        };
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            local.year(),
+            u8::from(local.month()),
+            local.day(),
+            local.hour(),
+            local.minute(),
+            local.second()
+        )?;
+        // TIME-SECFRAC is at most six digits
+        let micros = self.timestamp_nanos.unwrap_or(0) / 1000;
+        if micros > 0 {
+            write!(f, ".{}", format!("{:06}", micros).trim_end_matches('0'))?;
+        }
+        if self.timestamp_offset_unknown {
+            f.write_str("-00:00")
+        } else if offset_secs == 0 {
+            f.write_str("Z")
+        } else {
+            let sign = if offset_secs < 0 { '-' } else { '+' };
+            let minutes = offset_secs.unsigned_abs() / 60;
+            write!(f, "{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+        }
+    }
+}
+
+impl fmt::Display for SyslogMessage {
+    /// Render the message in RFC 5424 wire format
+    ///
+    /// PRI is computed from the facility and severity, absent fields are written as `-`, and SD
+    /// param values are re-escaped (and a BOM put back before the MSG if `msg_has_bom` is set), so
+    /// that parsing the output gives back an equal message. The
+    /// exceptions are the `trailer`, which isn't part of the wire format and is left out, `msg_raw`,
+    /// which can't be written to a formatter (`msg` is written instead),
+    /// fractional seconds finer than microseconds, which are truncated to the six digits RFC 5424
+    /// allows, and a `timestamp` set directly to a time outside the years 0000 to 9999, which is
+    /// written as `-`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use syslog_rfc5424::SyslogMessage;
+    ///
+    /// let line = r#"<78>1 2016-01-15T00:04:01.5+05:30 host1 CROND 10391 - [meta x="a\]b"] hello"#;
+    /// let mut msg: SyslogMessage = line.parse().unwrap();
+    /// assert_eq!(msg.to_string(), line);
+    ///
+    /// msg.sd.insert_tuple("meta", "x", "changed");
+    /// assert_eq!(
+    ///     msg.to_string(),
+    ///     r#"<78>1 2016-01-15T00:04:01.5+05:30 host1 CROND 10391 - [meta x="changed"] hello"#
+    /// );
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{}>{} ", self.priority(), self.version)?;
+        self.fmt_timestamp(f)?;
+        let procid = self.procid.as_ref().map(ProcId::to_string);
+        for field in &[
+            self.hostname.as_deref(),
+            self.appname.as_deref(),
+            procid.as_deref(),
+            self.msgid.as_deref(),
+        ] {
+            write!(f, " {}", field.unwrap_or("-"))?;
+        }
+        write!(f, " {}", self.sd)?;
+        if self.msg_has_bom {
+            write!(f, " \u{feff}{}", self.msg)?;
+        } else if !self.msg.is_empty() {
+            write!(f, " {}", self.msg)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds a `SyslogMessage` field by field; see `SyslogMessage::builder`
+#[derive(Clone, Debug)]
+pub struct SyslogMessageBuilder {
+    msg: SyslogMessage,
+}
+
+impl SyslogMessageBuilder {
+    pub fn severity(mut self, severity: severity::SyslogSeverity) -> Self {
+        self.msg.severity = severity;
+        self
+    }
+
+    pub fn facility(mut self, facility: facility::SyslogFacility) -> Self {
+        self.msg.facility = facility;
+        self
+    }
+
+    pub fn hostname<S: Into<name_t>>(mut self, hostname: S) -> Self {
+        self.msg.hostname = Some(hostname.into());
+        self
+    }
+
+    pub fn appname<S: Into<name_t>>(mut self, appname: S) -> Self {
+        self.msg.appname = Some(appname.into());
+        self
+    }
+
+    pub fn procid(mut self, procid: ProcId) -> Self {
+        self.msg.procid = Some(procid);
+        self
+    }
+
+    pub fn msgid<S: Into<msgid_t>>(mut self, msgid: S) -> Self {
+        self.msg.msgid = Some(msgid.into());
+        self
+    }
+
+    /// Set the timestamp, as for `SyslogMessage::with_timestamp` (which panics outside the years
+    /// 0000 to 9999)
+    pub fn timestamp(mut self, secs: time_t, nanos: u32) -> Self {
+        self.msg = self.msg.with_timestamp(secs, nanos);
+        self
+    }
+
+    /// Add one SD param, creating its element if this is the first param for `sd_id`
+    pub fn add_sd_param<SI, SPI, SPV>(
+        mut self,
+        sd_id: SI,
+        sd_param_id: SPI,
+        sd_param_value: SPV,
+    ) -> Self
+    where
+        SI: Into<SDIDType>,
+        SPI: Into<SDParamIDType>,
+        SPV: Into<SDParamValueType>,
+    {
+        self.msg.sd.insert_tuple(sd_id, sd_param_id, sd_param_value);
+        self
+    }
+
+    pub fn msg<S: Into<String>>(mut self, msg: S) -> Self {
+        self.msg.msg = msg.into();
+        self
+    }
+
+    pub fn build(self) -> SyslogMessage {
+        self.msg
+    }
+}
+
+impl SyslogMessage {
+    /// Start building a message
+    ///
+    /// Anything not set on the builder is left empty: no timestamp, hostname, app name, PROCID,
+    /// MSGID, or structured data, and an empty MSG. The facility and severity default to
+    /// `LOG_USER` and `SEV_NOTICE`, as for `syslog(3)`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use syslog_rfc5424::message::ProcId;
+    /// use syslog_rfc5424::{SyslogFacility, SyslogMessage, SyslogSeverity};
+    ///
+    /// let msg = SyslogMessage::builder()
+    ///     .facility(SyslogFacility::LOG_CRON)
+    ///     .severity(SyslogSeverity::SEV_INFO)
+    ///     .hostname("host1")
+    ///     .appname("CROND")
+    ///     .procid(ProcId::PID(10391))
+    ///     .add_sd_param("meta", "sequenceId", "29")
+    ///     .msg("some_message")
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     msg.to_rfc5424_string(),
+    ///     r#"<78>1 - host1 CROND 10391 - [meta sequenceId="29"] some_message"#
+    /// );
+    /// ```
+    pub fn builder() -> SyslogMessageBuilder {
+        SyslogMessageBuilder {
+            msg: SyslogMessage {
+                severity: severity::SyslogSeverity::SEV_NOTICE,
+                facility: facility::SyslogFacility::LOG_USER,
+                version: 1,
+                timestamp: None,
+                timestamp_nanos: None,
+                timestamp_offset_unknown: false,
+                timestamp_offset_secs: None,
+                hostname: None,
+                appname: None,
+                procid: None,
+                msgid: None,
+                sd: StructuredData::new_empty(),
+                msg: String::new(),
+                msg_has_bom: false,
+                msg_raw: None,
+                trailer: None,
+            },
+        }
+    }
+}
+
+impl FromStr for SyslogMessage {
+    type Err = parser::ParseErr;
+
+    /// Parse a string into a `SyslogMessage`
+    ///
+    /// Just calls `parser::parse_message`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parser::parse_message(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::enterprise_number;
+    use super::ProcId;
+    use super::StructuredData;
+    use super::SyslogMessage;
+    use super::{MultiStructuredData, StructuredDataMap};
+    #[cfg(feature = "std")]
+    use super::{SDIDType, SDParamIDType};
+    use crate::facility::SyslogFacility::*;
+    use crate::parser::{parse_message, parse_message_with};
+    use crate::severity::SyslogSeverity::*;
+    use crate::ParseOptions;
+    #[cfg(feature = "serde-serialize")]
+    use serde_json;
+
+    #[test]
+    fn test_structured_data_basic() {
+        let mut s = StructuredData::new_empty();
+        s.insert_tuple("foo", "bar", "baz");
+        let v = s.find_tuple("foo", "bar").expect("should find foo/bar");
+        assert_eq!(v, "baz");
+        assert!(s.find_tuple("foo", "baz").is_none());
+    }
+
+    #[test]
+    fn test_iter_elements() {
+        fn count_values<M: StructuredDataMap>(
+            sd: &M,
+            count: impl Fn(&M::Element) -> usize,
+        ) -> Vec<(String, usize)> {
+            sd.iter_elements()
+                .map(|(sd_id, element)| (String::from(sd_id), count(element)))
+                .collect()
+        }
+
+        let input = r#"<1>1 - - - - - [b x="1" x="2" y="3"][a][b z="4"] -"#;
+        let single = parse_message(input).unwrap().sd;
+        assert_eq!(
+            count_values(&single, |element| element.len()),
+            [(String::from("a"), 0), (String::from("b"), 3)]
+        );
+        let multi = parse_message_with::<MultiStructuredData>(input, &ParseOptions::default())
+            .unwrap()
+            .sd;
+        assert_eq!(
+            count_values(&multi, |element| element.values().map(Vec::len).sum()),
+            [(String::from("a"), 0), (String::from("b"), 4)]
+        );
+    }
+
+    #[test]
+    fn test_find_tuple_ignore_case() {
+        let msg = parse_message(
+            r#"<1>1 - - - - - [Meta sequenceId="1"][meta SEQUENCEID="2" language="en"][other x="y"] -"#,
+        )
+        .unwrap();
+        assert_eq!(msg.sd.find_tuple("meta", "sequenceId"), None);
+        // the first match in sorted order
+        assert_eq!(
+            msg.sd.find_tuple_ignore_case("meta", "sequenceId").unwrap(),
+            "1"
+        );
+        assert_eq!(
+            msg.sd.find_tuple_ignore_case("META", "Language").unwrap(),
+            "en"
+        );
+        // an exact match beats an earlier inexact one
+        assert_eq!(
+            msg.sd.find_tuple_ignore_case("meta", "SEQUENCEID").unwrap(),
+            "2"
+        );
+        assert_eq!(msg.sd.find_tuple_ignore_case("meta", "x"), None);
+        assert_eq!(msg.sd.find_tuple_ignore_case("missing", "x"), None);
+    }
+
+    #[cfg(feature = "serde-serialize")]
+    #[test]
+    fn test_structured_data_serialization_serde() {
+        let mut s = StructuredData::new_empty();
+        s.insert_tuple("foo", "bar", "baz");
+        s.insert_tuple("foo", "baz", "bar");
+        s.insert_tuple("faa", "bar", "baz");
+        let encoded = serde_json::to_string(&s).expect("Should encode to JSON");
         assert_eq!(
             encoded,
             r#"{"faa":{"bar":"baz"},"foo":{"bar":"baz","baz":"bar"}}"#
@@ -216,19 +1565,226 @@ mod tests {
             version: 1,
             timestamp: None,
             timestamp_nanos: None,
+            timestamp_offset_unknown: false,
+            timestamp_offset_secs: None,
             hostname: None,
             appname: None,
             procid: None,
             msgid: None,
             sd: StructuredData::new_empty(),
             msg: String::from(""),
+            msg_has_bom: false,
+            msg_raw: None,
+            trailer: None,
         };
 
         let encoded = serde_json::to_string(&m).expect("Should encode to JSON");
         // XXX: we don't have a guaranteed order, I don't think, so this might break with minor
         // version changes. *shrug*
         assert_eq!(encoded,
-                   "{\"severity\":\"info\",\"facility\":\"kern\",\"version\":1,\"timestamp\":null,\"timestamp_nanos\":null,\"hostname\":null,\"appname\":null,\"procid\":null,\"msgid\":null,\"sd\":{},\"msg\":\"\"}");
+                   "{\"severity\":\"info\",\"facility\":\"kern\",\"version\":1,\"timestamp\":null,\"timestamp_nanos\":null,\"timestamp_offset_unknown\":false,\"timestamp_offset_secs\":null,\"hostname\":null,\"appname\":null,\"procid\":null,\"msgid\":null,\"sd\":{},\"msg\":\"\",\"msg_has_bom\":false,\"msg_raw\":null,\"trailer\":null}");
+    }
+
+    #[cfg(feature = "serde-serialize")]
+    #[test]
+    fn test_deserialization_serde() {
+        let encoded = "{\"severity\":\"info\",\"facility\":\"kern\",\"version\":1,\"timestamp\":null,\"timestamp_nanos\":null,\"timestamp_offset_unknown\":false,\"timestamp_offset_secs\":null,\"hostname\":null,\"appname\":null,\"procid\":null,\"msgid\":null,\"sd\":{},\"msg\":\"\",\"msg_has_bom\":false,\"msg_raw\":null,\"trailer\":null}";
+        let m: SyslogMessage = serde_json::from_str(encoded).expect("Should decode from JSON");
+        assert_eq!(m.severity, SEV_INFO);
+        assert_eq!(m.facility, LOG_KERN);
+        assert_eq!(serde_json::to_string(&m).unwrap(), encoded);
+
+        for line in &[
+            r#"<78>1 2016-01-15T00:04:01.5+05:30 host1 CROND 10391 - [meta sequenceId="29"][x@1 a="b" c="d"] some_message"#,
+            "<165>1 - - app worker-7 ID47 - ",
+        ] {
+            let m = parse_message(line).unwrap();
+            let encoded = serde_json::to_string(&m).unwrap();
+            let decoded: SyslogMessage = serde_json::from_str(&encoded).unwrap();
+            assert_eq!(decoded, m);
+        }
+
+        assert_eq!(
+            serde_json::from_str::<ProcId>("42").unwrap(),
+            ProcId::PID(42)
+        );
+        assert_eq!(
+            serde_json::from_str::<ProcId>("\"42\"").unwrap(),
+            ProcId::Name(String::from("42"))
+        );
+        serde_json::from_str::<ProcId>("4294967296").expect_err("too big for a PID");
+        serde_json::from_str::<SyslogMessage>(&encoded.replace("kern", "nope"))
+            .expect_err("not a facility");
+    }
+
+    #[test]
+    fn test_to_rfc5424_string_round_trip() {
+        for line in &[
+            "<1>1 - - - - - -",
+            "<0>1 - - - - - - body",
+            "<191>1 2016-01-15T00:04:01Z host1 CROND 10391 ID47 - hello world",
+            "<13>1 2016-01-15T00:04:01.000001Z - app - - - -",
+            "<13>1 2017-07-26T14:47:35.869952+05:30 - - name - - x",
+            "<13>1 2017-07-26T14:47:35.5-03:30 h - - - - x",
+            "<13>1 2015-01-01T00:00:00-00:00 - - - - - unknown offset",
+            r#"<13>1 - - - - - [a x="q\"uote" y="back\\slash" z="br\]acket"][b e=""] m"#,
+        ] {
+            let msg = parse_message(line).expect("should parse");
+            assert_eq!(&msg.to_rfc5424_string(), line);
+            assert_eq!(parse_message(msg.to_string()).unwrap(), msg);
+        }
+    }
+
+    #[test]
+    fn test_fmt_timestamp_range() {
+        let msg = "<1>1 - - - - - -".parse::<SyslogMessage>().unwrap();
+        let cases = [
+            (
+                msg.clone().with_timestamp(0, 123_456_789),
+                "1970-01-01T00:00:00.123456Z",
+            ),
+            (msg.clone().with_timestamp(0, 999), "1970-01-01T00:00:00Z"),
+            (
+                msg.clone().with_timestamp(-62_167_219_200, 0),
+                "0000-01-01T00:00:00Z",
+            ),
+            (
+                msg.clone().with_timestamp(253_402_300_799, 999_999_999),
+                "9999-12-31T23:59:59.999999Z",
+            ),
+        ];
+        for (msg, timestamp) in &cases {
+            let line = msg.to_rfc5424_string();
+            assert_eq!(line, format!("<1>1 {} - - - - -", timestamp));
+            assert!(parse_message(&line).is_ok(), "{}", line);
+        }
+
+        for (timestamp, offset_secs) in &[
+            (-62_167_219_201, 0),
+            (300_000_000_000, 0),
+            (i64::MAX, 0),
+            (253_402_300_799, 3600),
+        ] {
+            let msg = SyslogMessage {
+                timestamp: Some(*timestamp),
+                timestamp_offset_secs: Some(*offset_secs),
+                ..msg.clone()
+            };
+            assert_eq!(msg.to_rfc5424_string(), "<1>1 - - - - - -");
+        }
+    }
+
+    #[test]
+    fn test_enterprise_number() {
+        assert_eq!(enterprise_number("origin"), None);
+        assert_eq!(enterprise_number("a@b@9"), Some("9"));
+        for bad in &["x@", "x@abc", "x@1.", "x@.1", "x@1..2", "x@-1"] {
+            assert_eq!(enterprise_number(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut msg = parse_message(
+            r#"<78>1 2016-01-15T00:04:01Z host1 CROND 10391 ID1 [meta x="1"] a long enough message"#,
+        )
+        .unwrap();
+        let capacity = msg.msg.capacity();
+        let ptr = msg.msg.as_ptr();
+        msg.clear();
+
+        assert_eq!(msg.msg, "");
+        assert_eq!(msg.msg.capacity(), capacity);
+        assert_eq!(msg.msg.as_ptr(), ptr);
+        assert!(msg.sd.is_empty());
+        assert_eq!(msg.timestamp, None);
+        assert_eq!(msg.timestamp_offset_secs, None);
+        assert_eq!(msg.hostname, None);
+        assert_eq!(msg.appname, None);
+        assert_eq!(msg.procid, None);
+        assert_eq!(msg.msgid, None);
+        assert_eq!(msg.facility, LOG_CRON);
+        assert_eq!(msg.severity, SEV_INFO);
+    }
+
+    #[test]
+    fn test_sd_display() {
+        let msg = parse_message("<1>1 - - - - - - hi").unwrap();
+        assert_eq!(msg.sd_display().to_string(), "-");
+
+        let msg = parse_message(r#"<1>1 - - - - - [a@1 x="\]"][b@1] hi"#).unwrap();
+        assert_eq!(msg.sd_display().to_string(), msg.sd.to_string());
+        assert_eq!(msg.sd_display().to_string(), r#"[a@1 x="\]"][b@1]"#);
+    }
+
+    #[test]
+    fn test_structured_data_canonical_bytes() {
+        let msg = parse_message("<1>1 - - - - - - -").unwrap();
+        assert_eq!(msg.structured_data_canonical_bytes(), b"-");
+
+        let msg = parse_message(
+            r#"<1>1 - - - - - [z@1 path="C:\dir" b="x"][a@1 q="say \"hi\"" a="1"] -"#,
+        )
+        .unwrap();
+        assert_eq!(
+            msg.structured_data_canonical_bytes(),
+            br#"[a@1 a="1" q="say \"hi\""][z@1 b="x" path="C:\\dir"]"#
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let msg = "<78>1 2016-01-15T00:04:01Z host1 CROND 10391 - - some_message"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        assert_eq!(
+            msg.summary(),
+            "[cron.info] host1 CROND[10391]: some_message"
+        );
+
+        let msg = "<165>1 - - app worker - - hi"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        assert_eq!(msg.summary(), "[local4.notice] - app[worker]: hi");
+
+        let msg = "<0>1 - - - - - -".parse::<SyslogMessage>().unwrap();
+        assert_eq!(msg.summary(), "[kern.emerg] - -:");
+    }
+
+    #[test]
+    fn test_to_rfc5424_string_built() {
+        let mut sd = StructuredData::new_empty();
+        sd.insert_tuple("meta", "path", r#"C:\dir]"quoted""#);
+        let msg = SyslogMessage {
+            severity: SEV_INFO,
+            facility: LOG_KERN,
+            version: 1,
+            timestamp: None,
+            timestamp_nanos: None,
+            timestamp_offset_unknown: false,
+            timestamp_offset_secs: None,
+            hostname: None,
+            appname: Some("app".into()),
+            procid: Some(ProcId::PID(42)),
+            msgid: None,
+            sd,
+            msg: String::new(),
+            msg_has_bom: false,
+            msg_raw: None,
+            trailer: Some(String::from("not emitted")),
+        }
+        .with_timestamp(1_452_816_241, 250_000_000);
+        let line = msg.to_rfc5424_string();
+        assert_eq!(
+            line,
+            r#"<6>1 2016-01-15T00:04:01.25Z - app 42 - [meta path="C:\\dir\]\"quoted\""]"#
+        );
+        let parsed = parse_message(&line).unwrap();
+        assert_eq!(
+            parsed.sd.find_tuple("meta", "path").map(String::as_str),
+            Some(r#"C:\dir]"quoted""#)
+        );
+        assert_eq!(parsed.trailer, None);
     }
 
     #[test]
@@ -242,6 +1798,427 @@ mod tests {
         assert_eq!("baz", s.get("faa").and_then(|foo| foo.get("bar")).unwrap());
     }
 
+    #[test]
+    fn test_fingerprint() {
+        let a = "<78>1 2016-01-15T00:04:01Z host1 CROND 10391 ID1 [meta sequenceId=\"29\"] one"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        let b = "<78>1 2017-03-02T11:00:00Z host1 CROND 5 ID1 [meta sequenceId=\"30\"] two"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        let c = "<78>1 2016-01-15T00:04:01Z host2 CROND 10391 ID1 [meta sequenceId=\"29\"] one"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_ne!(a.fingerprint(), c.fingerprint());
+        // pinned so that an accidental change to the algorithm is caught
+        assert_eq!(a.fingerprint(), 0xa922_a720_c5bd_e9bc);
+    }
+
+    #[test]
+    fn test_map_structured_data() {
+        let msg =
+            r#"<1>1 - - - - - [meta sequenceId="29" drop="me"][origin ip="10.0.0.1"][empty@1] m"#
+                .parse::<SyslogMessage>()
+                .unwrap()
+                .map_structured_data(|sd_id, param_id, value| match (sd_id, param_id) {
+                    (_, "drop") => None,
+                    ("origin", "ip") => Some((
+                        String::from("origin"),
+                        String::from("ip"),
+                        String::from("redacted"),
+                    )),
+                    _ => Some((sd_id.to_uppercase(), param_id.into(), value.into())),
+                });
+        assert_eq!(msg.sd.len(), 3);
+        assert_eq!(msg.sd.find_tuple("META", "sequenceId").unwrap(), "29");
+        assert!(msg.sd.find_tuple("META", "drop").is_none());
+        assert!(msg.sd.find_sdid("meta").is_none());
+        assert_eq!(msg.sd.find_tuple("origin", "ip").unwrap(), "redacted");
+        assert!(msg.sd.find_sdid("empty@1").unwrap().is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_sd_to_flat() {
+        let msg = r#"<1>1 - - - - - [meta sequenceId="29" a="b"][origin ip="10.0.0.1"][empty@1] m"#
+            .parse::<SyslogMessage>()
+            .unwrap();
+        let flat = msg.sd_to_flat();
+        assert_eq!(flat.len(), 3);
+        assert_eq!(
+            flat.get(&(SDIDType::from("meta"), SDParamIDType::from("sequenceId"))),
+            Some(&String::from("29"))
+        );
+        assert_eq!(
+            flat.get(&(SDIDType::from("origin"), SDParamIDType::from("ip"))),
+            Some(&String::from("10.0.0.1"))
+        );
+    }
+
+    #[test]
+    fn test_parse_msg_logfmt() {
+        let msg = r#"<1>1 - - - - - - level=warn msg="disk almost full" pct=97"#
+            .parse::<SyslogMessage>()
+            .unwrap();
+        let pairs = msg.parse_msg_logfmt().expect("should be logfmt");
+        assert_eq!(pairs["level"], "warn");
+        assert_eq!(pairs["msg"], "disk almost full");
+        assert_eq!(pairs["pct"], "97");
+
+        let msg = "<1>1 - - - - - - the disk is almost full"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        assert!(msg.parse_msg_logfmt().is_none());
+    }
+
+    #[test]
+    fn test_cee_payload() {
+        let msg = r#"<1>1 - - - - - - @cee: {"event":"login","user":"alice"}"#
+            .parse::<SyslogMessage>()
+            .unwrap();
+        assert_eq!(
+            msg.cee_payload(),
+            Some(r#"{"event":"login","user":"alice"}"#)
+        );
+        #[cfg(feature = "serde-serialize")]
+        assert_eq!(
+            msg.cee_json(),
+            Some(serde_json::json!({"event": "login", "user": "alice"}))
+        );
+
+        let msg = "<1>1 - - - - - - @cee:not json"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        assert_eq!(msg.cee_payload(), Some("not json"));
+        #[cfg(feature = "serde-serialize")]
+        assert_eq!(msg.cee_json(), None);
+
+        let msg = "<1>1 - - - - - - plain text mentioning @cee:"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        assert_eq!(msg.cee_payload(), None);
+    }
+
+    #[test]
+    fn test_find_params_by_prefix() {
+        let mut s = StructuredData::new_empty();
+        s.sd_entry("origin")
+            .set("x-b", "2")
+            .set("x-a", "1")
+            .set("x", "no dash")
+            .set("y-a", "other");
+        assert_eq!(
+            s.find_params_by_prefix("origin", "x-").collect::<Vec<_>>(),
+            vec![("x-a", "1"), ("x-b", "2")]
+        );
+        assert_eq!(s.find_params_by_prefix("origin", "").count(), 4);
+        assert_eq!(s.find_params_by_prefix("origin", "z").count(), 0);
+        assert_eq!(s.find_params_by_prefix("missing", "x-").count(), 0);
+    }
+
+    #[test]
+    fn test_contains() {
+        let mut s = StructuredData::new_empty();
+        s.insert_tuple("foo", "bar", "baz");
+        s.entry("empty");
+        assert!(s.contains_sdid("foo"));
+        assert!(s.contains_sdid("empty"));
+        assert!(!s.contains_sdid("bar"));
+        assert!(s.contains_tuple("foo", "bar"));
+        assert!(!s.contains_tuple("foo", "baz"));
+        assert!(!s.contains_tuple("empty", "bar"));
+    }
+
+    #[test]
+    fn test_sd_entry() {
+        let mut s = StructuredData::new_empty();
+        s.sd_entry("foo").remove("bar");
+        assert!(!s.contains_sdid("foo"));
+
+        s.sd_entry("foo")
+            .set("bar", "1")
+            .set("baz", "2")
+            .remove("bar");
+        assert!(!s.contains_tuple("foo", "bar"));
+        assert_eq!(s.sd_entry("foo").get("baz").map(String::as_str), Some("2"));
+
+        s.sd_entry("foo").remove("baz");
+        assert!(s.contains_sdid("foo"));
+        assert_eq!(s.find_sdid("foo").map(|e| e.len()), Some(0));
+
+        s.sd_entry("other")
+            .or_default()
+            .insert("a".into(), "b".into());
+        assert_eq!(s.sd_entry("other").sd_id(), "other");
+        assert!(s.sd_entry("foo").remove_entry().is_some());
+        assert!(!s.contains_sdid("foo"));
+        assert_eq!(s.len(), 1);
+    }
+
+    #[test]
+    fn test_json_sd_value() {
+        let msg = r#"<1>1 - - - - - [app@1 payload="{\"k\":\"v\"}" bad="{"] -"#
+            .parse::<SyslogMessage>()
+            .unwrap();
+        assert_eq!(
+            msg.sd.find_tuple("app@1", "payload"),
+            Some(&r#"{"k":"v"}"#.to_string())
+        );
+        #[cfg(feature = "serde-serialize")]
+        {
+            assert_eq!(
+                msg.sd.find_tuple_json("app@1", "payload"),
+                Some(serde_json::json!({"k": "v"}))
+            );
+            assert_eq!(msg.sd.find_tuple_json("app@1", "bad"), None);
+            assert_eq!(msg.sd.find_tuple_json("app@1", "missing"), None);
+        }
+    }
+
+    #[test]
+    fn test_with_timestamp() {
+        let msg = "<1>1 2003-10-11T22:14:15.003Z - - - - -"
+            .parse::<SyslogMessage>()
+            .unwrap()
+            .with_timestamp(1_500_000_000, 1_250_000_000);
+        assert_eq!(msg.timestamp, Some(1_500_000_001));
+        assert_eq!(msg.timestamp_nanos, Some(250_000_000));
+
+        #[cfg(feature = "std")]
+        {
+            let msg = "<1>1 - - - - - -"
+                .parse::<SyslogMessage>()
+                .unwrap()
+                .with_timestamp_now();
+            assert!(msg.timestamp.unwrap() > 1_500_000_000);
+            assert!(msg.timestamp_nanos.unwrap() < 1_000_000_000);
+        }
+
+        let msg = msg.with_timestamp(-62_167_219_200, 0);
+        assert_eq!(msg.timestamp, Some(-62_167_219_200));
+        let msg = msg.with_timestamp(253_402_300_798, 1_999_999_000);
+        assert_eq!(msg.timestamp, Some(253_402_300_799));
+        assert_eq!(msg.timestamp_nanos, Some(999_999_000));
+    }
+
+    #[test]
+    #[should_panic(expected = "timestamp outside the years 0000 to 9999")]
+    fn test_with_timestamp_overflow() {
+        let msg = "<1>1 - - - - - -".parse::<SyslogMessage>().unwrap();
+        let _ = msg.with_timestamp(i64::MAX, 1_000_000_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "timestamp outside the years 0000 to 9999")]
+    fn test_with_timestamp_out_of_range() {
+        let msg = "<1>1 - - - - - -".parse::<SyslogMessage>().unwrap();
+        let _ = msg.with_timestamp(-62_167_219_201, 0);
+    }
+
+    #[test]
+    fn test_builder() {
+        let msg = SyslogMessage::builder()
+            .facility(LOG_LOCAL0)
+            .severity(SEV_ERR)
+            .timestamp(1_452_816_241, 500_000_000)
+            .hostname("host1")
+            .appname(String::from("app"))
+            .procid(ProcId::Name(String::from("worker-1")))
+            .msgid("ID47")
+            .add_sd_param("meta", "sequenceId", "29")
+            .add_sd_param("meta", "x", r#"a"b"#)
+            .add_sd_param("origin", "ip", "192.0.2.1")
+            .msg("hello")
+            .build();
+        let line = msg.to_rfc5424_string();
+        assert_eq!(
+            line,
+            r#"<131>1 2016-01-15T00:04:01.5Z host1 app worker-1 ID47 [meta sequenceId="29" x="a\"b"][origin ip="192.0.2.1"] hello"#
+        );
+        assert_eq!(parse_message(&line).unwrap(), msg);
+
+        let msg = SyslogMessage::builder().build();
+        assert_eq!(msg.to_rfc5424_string(), "<13>1 - - - - - -");
+    }
+
+    #[test]
+    fn test_timestamp_timespec() {
+        let msg = "<1>1 1985-04-12T23:20:50.52Z - - - - -"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        assert_eq!(msg.timestamp_timespec(), Some((482196050, 520_000_000)));
+        let msg = "<1>1 - - - - - -".parse::<SyslogMessage>().unwrap();
+        assert_eq!(msg.timestamp_timespec(), None);
+    }
+
+    #[test]
+    fn test_offset_datetime() {
+        let msg = "<1>1 1985-04-12T19:20:50.52-04:00 - - - - -"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        let datetime = msg.offset_datetime().unwrap();
+        assert_eq!(datetime.unix_timestamp(), 482196050);
+        assert_eq!(datetime.nanosecond(), 520_000_000);
+        assert_eq!(datetime.offset().whole_seconds(), -4 * 3600);
+        assert_eq!((datetime.hour(), datetime.minute()), (19, 20));
+
+        let msg = "<1>1 1985-04-12T23:20:50Z - - - - -"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        assert!(msg.offset_datetime().unwrap().offset().is_utc());
+
+        let msg = "<1>1 - - - - - -".parse::<SyslogMessage>().unwrap();
+        assert_eq!(msg.offset_datetime(), None);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono_datetime() {
+        use chrono::Timelike;
+
+        let msg = "<1>1 1985-04-12T19:20:50.52-04:00 - - - - -"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        let datetime = msg.chrono_datetime().unwrap();
+        assert_eq!(datetime.timestamp(), 482196050);
+        assert_eq!(datetime.offset().local_minus_utc(), -4 * 3600);
+        assert_eq!((datetime.hour(), datetime.nanosecond()), (19, 520_000_000));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_datetime() {
+        let msg = "<1>1 1985-04-12T19:20:50.52-04:00 - - - - -"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        let datetime = msg.datetime().unwrap();
+        assert_eq!(datetime.timestamp(), 482196050);
+        assert_eq!(datetime.timestamp_subsec_nanos(), 520_000_000);
+
+        let msg = "<1>1 - - - - - -".parse::<SyslogMessage>().unwrap();
+        assert_eq!(msg.datetime(), None);
+
+        // out of chrono's range, rather than panicking
+        let msg = SyslogMessage {
+            timestamp: Some(i64::MAX),
+            ..msg
+        };
+        assert_eq!(msg.datetime(), None);
+    }
+
+    #[test]
+    fn test_timestamp_in_offset() {
+        let msg = "<1>1 1985-04-12T23:20:50.52-04:00 - - - - -"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        let tokyo = time::UtcOffset::from_hms(9, 0, 0).unwrap();
+        let local = msg.timestamp_in_offset(tokyo).unwrap();
+        assert_eq!(local.offset(), tokyo);
+        assert_eq!(local.unix_timestamp(), msg.timestamp.unwrap());
+        assert_eq!(
+            (
+                local.day(),
+                local.hour(),
+                local.minute(),
+                local.nanosecond()
+            ),
+            (13, 12, 20, 520_000_000)
+        );
+
+        let msg = "<1>1 - - - - - -".parse::<SyslogMessage>().unwrap();
+        assert_eq!(msg.timestamp_in_offset(tokyo), None);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_timestamp_in_timezone() {
+        use chrono::{FixedOffset, Timelike};
+
+        let msg = "<1>1 1985-04-12T23:20:50.52Z - - - - -"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        let kolkata = FixedOffset::east_opt(5 * 3600 + 1800).unwrap();
+        let local = msg.timestamp_in_timezone(&kolkata).unwrap();
+        assert_eq!(local.timestamp(), 482196050);
+        assert_eq!((local.hour(), local.minute()), (4, 50));
+        assert_eq!(local.nanosecond(), 520_000_000);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_age() {
+        let msg = "<1>1 - - - - - -".parse::<SyslogMessage>().unwrap();
+        assert_eq!(msg.age(), None);
+
+        let age = msg.clone().with_timestamp_now().age().unwrap();
+        assert!(age < std::time::Duration::from_secs(60));
+
+        let age = msg.clone().with_timestamp(1_000_000_000, 0).age().unwrap();
+        assert!(age > std::time::Duration::from_secs(20 * 365 * 86400));
+
+        let future = msg.with_timestamp(32_503_680_000, 0);
+        assert_eq!(future.age(), Some(std::time::Duration::ZERO));
+    }
+
+    #[test]
+    fn test_strip_relay_prefix() {
+        let mut msg = "<1>1 - relay - - - - web1.example.com: GET /index.html"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        assert_eq!(
+            msg.strip_relay_prefix(),
+            Some("web1.example.com".to_string())
+        );
+        assert_eq!(msg.msg, "GET /index.html");
+        assert_eq!(msg.strip_relay_prefix(), None);
+        assert_eq!(msg.msg, "GET /index.html");
+
+        for body in &[
+            "no prefix here",
+            "two words: not a host",
+            "-bad-: leading hyphen",
+            "a..b: empty label",
+            "host:no space",
+            ": empty",
+        ] {
+            let mut msg = format!("<1>1 - - - - - - {}", body)
+                .parse::<SyslogMessage>()
+                .unwrap();
+            assert_eq!(msg.strip_relay_prefix(), None, "{:?}", body);
+            assert_eq!(msg.msg, *body);
+        }
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let bytes = (0..4096u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect::<Vec<_>>();
+        let mut u = Unstructured::new(&bytes);
+        for _ in 0..16 {
+            let msg = SyslogMessage::arbitrary(&mut u).expect("should generate");
+            for (term, max_len) in &[
+                (msg.hostname.as_deref(), 255),
+                (msg.appname.as_deref(), 48),
+                (msg.msgid.as_deref(), 32),
+            ] {
+                if let Some(term) = term {
+                    assert!(!term.is_empty() && term.len() <= *max_len);
+                    assert!(term.bytes().all(|b| (b'!'..=b'~').contains(&b)));
+                    assert_ne!(*term, "-");
+                }
+            }
+            for (sd_id, params) in msg.sd.iter() {
+                assert!(!sd_id.contains(['=', ']', '"']));
+                assert!(params.keys().all(|k| !k.contains('=')));
+            }
+        }
+    }
+
     #[test]
     fn test_fromstr() {
         let msg = "<1>1 1985-04-12T23:20:50.52Z host - - - -"
@@ -249,4 +2226,22 @@ mod tests {
             .expect("Should parse empty message");
         assert_eq!(msg.timestamp, Some(482196050));
     }
+
+    #[test]
+    fn test_field_comparisons() {
+        let msg = "<1>1 - host app 1234 ID47 - -"
+            .parse::<SyslogMessage>()
+            .expect("should parse");
+        assert_eq!(msg.msgid, Some("ID47".to_string()));
+        assert_eq!(msg.msgid.as_deref(), Some("ID47"));
+        let procid = msg.procid.expect("has a procid");
+        assert_eq!(procid, ProcId::PID(1234));
+        assert_eq!(procid, "1234");
+        assert_ne!(procid, "01234x");
+        assert_eq!(procid.to_string(), "1234");
+
+        let name = ProcId::Name("worker".to_string());
+        assert_eq!(name, "worker");
+        assert_eq!(name.to_string(), "worker");
+    }
 }
@@ -1,17 +1,27 @@
-use std::borrow::Cow;
-use std::convert::TryFrom;
-use std::num;
-use std::str;
-use std::str::FromStr;
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use core::num;
+use core::str;
+use core::str::FromStr;
 
+#[cfg(feature = "serde-serialize")]
+use serde::Serialize;
 use thiserror::Error;
 
+use crate::borrowed::SyslogMessageRef;
 use crate::facility;
-use crate::message::{ProcId, StructuredData, SyslogMessage};
+#[cfg(feature = "interning")]
+use crate::interner::Interner;
+use crate::message::{self, ProcId, StructuredData, StructuredDataMap, SyslogMessage};
 use crate::severity;
 
+/// What went wrong while parsing; see `ParseErr`
 #[derive(Debug, Error)]
-pub enum ParseErr {
+pub enum ParseErrKind {
     #[error("regular expression does not parse")]
     RegexDoesNotMatchErr,
     #[error("bad severity in message")]
@@ -29,7 +39,7 @@ pub enum ParseErr {
     #[error("unicode error: {0}")]
     BaseUnicodeError(#[from] str::Utf8Error),
     #[error("unicode error: {0}")]
-    UnicodeError(#[from] std::string::FromUtf8Error),
+    UnicodeError(#[from] alloc::string::FromUtf8Error),
     #[error("unexpected input at character {0}")]
     ExpectedTokenErr(char),
     #[error("integer conversion error: {0}")]
@@ -42,6 +52,491 @@ pub enum ParseErr {
     InvalidDate(String),
     #[error("date had invalid UTC offset")]
     InvalidOffset,
+    #[error("expected a space between fields but found {0:?}")]
+    BadSeparator(char),
+    #[error("parser step limit exceeded")]
+    StepLimitExceeded,
+    #[error("invalid octet count")]
+    InvalidOctetCount,
+    #[error("input is not valid {0}")]
+    InvalidInputEncoding(&'static str),
+    #[error("invalid SD name {0:?}")]
+    InvalidSdName(String),
+    #[error("expected {expected} but found {}", describe_found(*.found))]
+    ExpectedClass {
+        expected: CharClass,
+        found: Option<char>,
+    },
+    #[error("field {0} is longer than RFC 5424 allows")]
+    FieldTooLong(&'static str),
+}
+
+fn describe_found(found: Option<char>) -> String {
+    match found {
+        Some(c) => format!("{:?}", c),
+        None => String::from("end of input"),
+    }
+}
+
+/// A kind of character the parser expected; see `ParseErrKind::ExpectedClass`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CharClass {
+    /// An ASCII digit
+    Digit,
+    /// A printable character: ASCII `!` through `~`, plus non-ASCII characters in the header
+    /// fields with `ParseOptions::allow_utf8_in_header`
+    Printable,
+}
+
+impl fmt::Display for CharClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            CharClass::Digit => "a digit",
+            CharClass::Printable => "a printable character",
+        })
+    }
+}
+
+/// An error from parsing a message, along with where in the input it happened
+#[derive(Debug, Error)]
+#[error("{kind} at byte {offset}")]
+pub struct ParseErr {
+    kind: ParseErrKind,
+    offset: usize,
+}
+
+impl ParseErr {
+    /// What went wrong
+    pub fn kind(&self) -> &ParseErrKind {
+        &self.kind
+    }
+
+    /// Discard the position, keeping only what went wrong
+    pub fn into_kind(self) -> ParseErrKind {
+        self.kind
+    }
+
+    /// The byte offset in the input at which parsing stopped: the first byte that couldn't be
+    /// consumed, or the start of a field whose value turned out to be invalid (such as an
+    /// impossible date). Errors which aren't about any particular position, such as a bad raw
+    /// priority passed to `from_priority`, have an offset of 0.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// An error partway through parsing: what went wrong, and how much of the input was left
+/// unconsumed at that point
+///
+/// Every parsing function works on a suffix of the original input, so the amount left over pins
+/// down the position without having to thread an offset through every call; the public entry
+/// points turn it into a `ParseErr` with an absolute offset.
+#[derive(Debug)]
+struct Failure {
+    kind: ParseErrKind,
+    remaining: usize,
+}
+
+impl Failure {
+    /// A failure at the start of `rest`
+    fn at(kind: ParseErrKind, rest: &str) -> Self {
+        Failure {
+            kind,
+            remaining: rest.len(),
+        }
+    }
+
+    /// A failure at the start of `rest`, which doesn't begin with a character of class `expected`
+    fn expected(expected: CharClass, rest: &str) -> Self {
+        Self::at(
+            ParseErrKind::ExpectedClass {
+                expected,
+                found: rest.chars().next(),
+            },
+            rest,
+        )
+    }
+
+    /// Locate the failure within `input`, the full string that parsing started from
+    fn locate(self, input: &str) -> ParseErr {
+        ParseErr {
+            kind: self.kind,
+            offset: input.len().saturating_sub(self.remaining),
+        }
+    }
+}
+
+/// Options controlling how permissive the parser is
+///
+/// The default options follow RFC 5424, except that SD names are only checked if
+/// `strict_sd_names` is set; every other setting relaxes the grammar in some specific way to
+/// accommodate a class of misbehaving senders.
+///
+/// With the `strict-only` feature, every one of those settings is removed, leaving only
+/// `max_steps`, `input_encoding`, and `utf8_mode`, so that nothing in the program can turn on
+/// permissive parsing.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Allow non-ASCII UTF-8 characters in the HOSTNAME, APP-NAME, PROCID, and MSGID fields, which
+    /// the RFC restricts to printable US-ASCII. Field lengths are then counted in characters
+    /// rather than bytes.
+    #[cfg(not(feature = "strict-only"))]
+    pub allow_utf8_in_header: bool,
+    /// Accept a carriage return immediately before the space that separates two fields (i.e.
+    /// treat `"\r "` as the separator), as emitted by some broken senders.
+    #[cfg(not(feature = "strict-only"))]
+    pub allow_cr_before_separator: bool,
+    /// Abort with `ParseErrKind::StepLimitExceeded` once the parser has done more than this much
+    /// work, as a defense against pathological input. Roughly one step is charged per character
+    /// of input examined. `None` (the default) means no limit.
+    pub max_steps: Option<usize>,
+    /// Decode PRI as `severity << 3 | facility` rather than `facility << 3 | severity`, for
+    /// interoperability with an encoder known to swap them. Since only three bits are left for
+    /// the facility, this can only represent facilities 0 through 7.
+    #[cfg(not(feature = "strict-only"))]
+    pub swapped_pri: bool,
+    /// Accept messages which omit the STRUCTURED-DATA field altogether (not even a `-`), going
+    /// straight from MSGID to MSG. If the token after MSGID starts with neither `[` nor `-`, the
+    /// structured data is taken to be empty and that token starts the message. A message which
+    /// itself begins with `-` or `[` can't be told apart from structured data this way.
+    #[cfg(not(feature = "strict-only"))]
+    pub allow_missing_sd: bool,
+    /// Accept flag-style SD params consisting of a bare name with no `="value"` (as in
+    /// `[flags secure verbose]`), storing them with an empty value
+    #[cfg(not(feature = "strict-only"))]
+    pub allow_bare_sd_params: bool,
+    /// Accept `:` in place of `=` between an SD param name and its value (as in
+    /// `[meta sequenceId:"29"]`), as written by some JSON-minded emitters. A colon elsewhere in a
+    /// param name is still part of the name.
+    #[cfg(not(feature = "strict-only"))]
+    pub allow_colon_sd_separator: bool,
+    /// Interpret the C-style escapes `\n`, `\t`, and `\r` in SD param values as the
+    /// corresponding control characters. Otherwise (as the RFC requires) only `\"`, `\\`, and
+    /// `\]` are escapes, and a backslash before anything else is kept literally.
+    #[cfg(not(feature = "strict-only"))]
+    pub allow_c_escapes: bool,
+    /// Skip spaces between the PRI and the VERSION (as in `<13> 1 ...`)
+    #[cfg(not(feature = "strict-only"))]
+    pub allow_space_before_version: bool,
+    /// Accept more than the six fractional-second digits allowed by RFC 5424, keeping nine
+    /// (nanosecond resolution). The same as `max_frac_digits: Some(9)`.
+    #[cfg(not(feature = "strict-only"))]
+    pub allow_long_fractional_seconds: bool,
+    /// Read at most this many fractional-second digits, and discard any after them according to
+    /// `fractional_rounding`, rather than failing with `ParseErrKind::TooManyDigits` after six
+    /// as RFC 5424 requires (`None`). Nothing past nine digits (nanosecond resolution) is ever
+    /// kept, so larger limits behave like `Some(9)`, which is also what
+    /// `allow_long_fractional_seconds` means. When this is set, that flag is ignored.
+    #[cfg(not(feature = "strict-only"))]
+    pub max_frac_digits: Option<usize>,
+    /// How to fold discarded fractional-second digits into the kept ones, when
+    /// `allow_long_fractional_seconds` or `max_frac_digits` lets them through
+    #[cfg(not(feature = "strict-only"))]
+    pub fractional_rounding: Rounding,
+    /// How `parse_message_bytes_with_options` treats input which isn't valid UTF-8. RFC 5424
+    /// allows any octets in the MSG, so unlike the settings above this is kept in `strict-only`
+    /// builds.
+    pub utf8_mode: Utf8Mode,
+    /// How the whole message is wrapped for transport, and so must be decoded before parsing.
+    /// The offsets in any `ParseErr` then refer to the decoded message.
+    pub input_encoding: InputEncoding,
+    /// Accept a timestamp whose fractional seconds are empty or a `-` (as in `...:50.Z` or
+    /// `...:50.-Z`), treating it as having no fraction
+    #[cfg(not(feature = "strict-only"))]
+    pub allow_empty_fraction: bool,
+    /// Accept a single digit for the month, day, hour, minute, or second of a timestamp (as in
+    /// `2016-1-5T0:4:1Z`), rather than requiring exactly two
+    #[cfg(not(feature = "strict-only"))]
+    pub allow_unpadded_timestamp_fields: bool,
+    /// Accept a space in place of the `T` between the date and the time of a timestamp (as in
+    /// `2016-01-15 00:04:01Z`)
+    #[cfg(not(feature = "strict-only"))]
+    pub allow_space_time_separator: bool,
+    /// Accept a lowercase `t` between the date and the time of a timestamp, and a lowercase `z`
+    /// for UTC, as RFC 3339 permits but RFC 5424 does not
+    #[cfg(not(feature = "strict-only"))]
+    pub allow_lowercase_t_and_z: bool,
+    /// Accept a bare Unix epoch integer of 9 or 10 digits (as in `<1>1 1452816241 host ...`) in
+    /// place of the RFC 3339 timestamp
+    #[cfg(not(feature = "strict-only"))]
+    pub allow_epoch_timestamp: bool,
+    /// Treat a PRI of exactly `<0>` as "no priority given" rather than kern.emerg, using
+    /// `default_facility` and `default_severity` instead
+    #[cfg(not(feature = "strict-only"))]
+    pub pri_zero_as_default: bool,
+    /// The facility used for `<0>` when `pri_zero_as_default` is set; `LOG_USER` if `None`
+    #[cfg(not(feature = "strict-only"))]
+    pub default_facility: Option<facility::SyslogFacility>,
+    /// The severity used for `<0>` when `pri_zero_as_default` is set; `SEV_NOTICE` if `None`
+    #[cfg(not(feature = "strict-only"))]
+    pub default_severity: Option<severity::SyslogSeverity>,
+    /// If set, everything after the last occurrence of this character in the MSG is split off
+    /// into `SyslogMessage::trailer`, for stripping a checksum or sequence number appended by
+    /// the transport
+    #[cfg(not(feature = "strict-only"))]
+    pub trailing_field_delimiter: Option<char>,
+    /// Reject SD-IDs and param names which aren't a valid RFC 5424 SD-NAME (1 to 32 printable
+    /// ASCII characters other than `=`, space, `]`, `"`, and `@`) with
+    /// `ParseErrKind::InvalidSdName`. An SD-ID may also take the enterprise form `name@number`.
+    /// Off by default, in which case a name runs up to the next space, `=`, or `]`; always on in
+    /// `strict-only` builds.
+    #[cfg(not(feature = "strict-only"))]
+    pub strict_sd_names: bool,
+    /// Accept a PRI written as separate decimal facility and severity values (as in `<9.6>` for
+    /// cron.info), rather than only the combined `facility * 8 + severity`. `swapped_pri` has no
+    /// effect on this form.
+    #[cfg(not(feature = "strict-only"))]
+    pub allow_dotted_pri: bool,
+    /// Keep any extra space-separated fields between the MSGID and the structured data (as in
+    /// `... msgid extra1 extra2 [meta x="1"] msg`) as the params `1`, `2`, and so on of a
+    /// synthetic `_extra` SD element, rather than failing to parse. An extra field which is just
+    /// `-` can't be told apart from nil structured data, and ends the extra fields. If no
+    /// structured data follows at all, nothing is collected.
+    #[cfg(not(feature = "strict-only"))]
+    pub collect_extra_header_fields: bool,
+}
+
+/// Readers for the permissive settings, which are always off in `strict-only` builds
+macro_rules! permissive_options {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        impl ParseOptions {
+            $(
+                #[cfg(not(feature = "strict-only"))]
+                #[inline]
+                fn $field(&self) -> $ty {
+                    self.$field
+                }
+
+                #[cfg(feature = "strict-only")]
+                #[inline]
+                fn $field(&self) -> $ty {
+                    <$ty>::default()
+                }
+            )*
+        }
+    };
+}
+
+permissive_options! {
+    allow_utf8_in_header: bool,
+    allow_cr_before_separator: bool,
+    swapped_pri: bool,
+    allow_missing_sd: bool,
+    allow_bare_sd_params: bool,
+    allow_colon_sd_separator: bool,
+    allow_c_escapes: bool,
+    allow_space_before_version: bool,
+    allow_long_fractional_seconds: bool,
+    max_frac_digits: Option<usize>,
+    fractional_rounding: Rounding,
+    allow_empty_fraction: bool,
+    allow_unpadded_timestamp_fields: bool,
+    allow_space_time_separator: bool,
+    allow_lowercase_t_and_z: bool,
+    allow_epoch_timestamp: bool,
+    pri_zero_as_default: bool,
+    default_facility: Option<facility::SyslogFacility>,
+    default_severity: Option<severity::SyslogSeverity>,
+    trailing_field_delimiter: Option<char>,
+    allow_dotted_pri: bool,
+    collect_extra_header_fields: bool,
+}
+
+impl ParseOptions {
+    /// Options which accept every common deviation from RFC 5424 that can't change how a valid
+    /// message is read
+    ///
+    /// These are the settings which only let through input that would otherwise be rejected:
+    ///
+    ///  * `allow_utf8_in_header`: non-ASCII UTF-8 in HOSTNAME, APP-NAME, PROCID, and MSGID
+    ///  * `allow_cr_before_separator`: `"\r "` between fields
+    ///  * `allow_missing_sd`: no STRUCTURED-DATA field at all
+    ///  * `allow_bare_sd_params`: SD params with no `="value"`
+    ///  * `allow_colon_sd_separator`: `:` in place of `=` in SD params
+    ///  * `allow_space_before_version`: spaces between the PRI and the VERSION
+    ///  * `allow_long_fractional_seconds`: more than six fractional-second digits, truncated to
+    ///    nanoseconds
+    ///  * `allow_empty_fraction`: a `.` with no fractional-second digits after it
+    ///  * `allow_unpadded_timestamp_fields`: one-digit month, day, hour, minute, or second
+    ///  * `allow_space_time_separator`: a space in place of the `T` in the timestamp
+    ///  * `allow_lowercase_t_and_z`: `t` and `z` in place of `T` and `Z` in the timestamp
+    ///  * `allow_epoch_timestamp`: a Unix epoch integer in place of the timestamp
+    ///  * `allow_dotted_pri`: a PRI written as `<facility.severity>`
+    ///
+    /// The settings which reinterpret otherwise valid input (`swapped_pri`, `allow_c_escapes`,
+    /// `pri_zero_as_default`, `trailing_field_delimiter`), or which make up structured data
+    /// (`collect_extra_header_fields`), are left off, as are `max_steps`, `input_encoding`, and
+    /// `utf8_mode`.
+    ///
+    /// In `strict-only` builds there is nothing to turn on, and this is the same as
+    /// `ParseOptions::default()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use syslog_rfc5424::{parse_message_with_options, ParseOptions};
+    ///
+    /// let line = "<13> 1 2016-01-15 00:04:01.1234567Z host1 - - - hello";
+    /// let msg = parse_message_with_options(line, &ParseOptions::lenient());
+    /// # #[cfg(not(feature = "strict-only"))]
+    /// assert_eq!(msg.unwrap().msg, "hello");
+    /// ```
+    pub fn lenient() -> Self {
+        #[cfg_attr(feature = "strict-only", allow(unused_mut))]
+        let mut opts = ParseOptions::default();
+        #[cfg(not(feature = "strict-only"))]
+        {
+            opts.allow_utf8_in_header = true;
+            opts.allow_cr_before_separator = true;
+            opts.allow_missing_sd = true;
+            opts.allow_bare_sd_params = true;
+            opts.allow_colon_sd_separator = true;
+            opts.allow_space_before_version = true;
+            opts.allow_long_fractional_seconds = true;
+            opts.allow_empty_fraction = true;
+            opts.allow_unpadded_timestamp_fields = true;
+            opts.allow_space_time_separator = true;
+            opts.allow_lowercase_t_and_z = true;
+            opts.allow_epoch_timestamp = true;
+            opts.allow_dotted_pri = true;
+        }
+        opts
+    }
+
+    #[cfg(not(feature = "strict-only"))]
+    #[inline]
+    fn strict_sd_names(&self) -> bool {
+        self.strict_sd_names
+    }
+
+    #[cfg(feature = "strict-only")]
+    #[inline]
+    fn strict_sd_names(&self) -> bool {
+        true
+    }
+}
+
+/// How to reduce a value with more precision than can be stored
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rounding {
+    /// Drop the extra digits
+    #[default]
+    Truncate,
+    /// Round to the nearest representable value, with ties rounding up
+    HalfUp,
+}
+
+/// How to handle invalid UTF-8 when parsing raw bytes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Utf8Mode {
+    /// Reject the message if it contains invalid UTF-8 anywhere
+    #[default]
+    Strict,
+    /// Replace invalid sequences in the MSG with U+FFFD; the header and structured data must
+    /// still be valid UTF-8
+    LossyMsg,
+}
+
+/// An encoding wrapped around an entire message, undone before parsing it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputEncoding {
+    /// The input is the message itself
+    #[default]
+    None,
+    /// The message is base64-encoded, with the standard alphabet and optional padding. Only
+    /// available with the `base64` feature.
+    #[cfg(feature = "base64")]
+    Base64,
+    /// The message is percent-encoded, as in a URL: every `%XX` is replaced by the byte with hex
+    /// value `XX`, and everything else (including `+`) is left alone
+    Percent,
+}
+
+/// A non-fatal oddity noticed while parsing a message
+///
+/// Warnings are only collected by `parse_message_with_warnings`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseWarning {
+    /// A param appeared more than once in the same SD-ID (possibly across several SD-ELEMENTs),
+    /// and all but its last value were discarded
+    DuplicateSdParam { sd_id: String, param: String },
+    /// The MSG ended in whitespace, which often means that bytes from the framing (such as a
+    /// line terminator or padding) leaked into the body
+    TrailingWhitespace,
+    /// The timestamp was a leap second (`:60`), which is stored as 59.999999 seconds since
+    /// Unix time can't represent it
+    LeapSecond,
+}
+
+/// Per-message state threaded through the parser
+pub(crate) struct ParseContext<'o> {
+    opts: &'o ParseOptions,
+    /// `None` unless the caller asked for warnings, so that the common case never builds them
+    warnings: Option<Vec<ParseWarning>>,
+    steps: usize,
+    /// Byte offset at which the MSG started, once the header has been parsed
+    msg_offset: usize,
+    #[cfg(feature = "interning")]
+    interner: Option<&'o mut Interner>,
+    #[cfg(feature = "sd-interning")]
+    sd_interner: Option<&'o mut Interner>,
+}
+
+impl<'o> ParseContext<'o> {
+    fn new(opts: &'o ParseOptions) -> Self {
+        ParseContext {
+            opts,
+            warnings: None,
+            steps: 0,
+            msg_offset: 0,
+            #[cfg(feature = "interning")]
+            interner: None,
+            #[cfg(feature = "sd-interning")]
+            sd_interner: None,
+        }
+    }
+
+    /// Build a HOSTNAME or APP-NAME, sharing storage through the interner if there is one
+    fn name(&mut self, s: &str) -> message::name_t {
+        #[cfg(feature = "interning")]
+        {
+            if let Some(interner) = self.interner.as_mut() {
+                return interner.intern(s);
+            }
+        }
+        message::name_t::from(s)
+    }
+
+    /// Build an SD-ID or param name, sharing storage through the SD interner if there is one
+    fn sd_name(&mut self, s: &str) -> message::SDIDType {
+        #[cfg(feature = "sd-interning")]
+        {
+            if let Some(interner) = self.sd_interner.as_mut() {
+                return interner.intern(s);
+            }
+        }
+        message::SDIDType::from(s)
+    }
+
+    /// Account for `n` steps of parser work, having got as far as `rest`
+    fn step(&mut self, n: usize, rest: &str) -> ParseResult<()> {
+        self.steps += n;
+        match self.opts.max_steps {
+            Some(max_steps) if self.steps > max_steps => {
+                Err(Failure::at(ParseErrKind::StepLimitExceeded, rest))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn warn<F>(&mut self, f: F)
+    where
+        F: FnOnce() -> ParseWarning,
+    {
+        if let Some(warnings) = self.warnings.as_mut() {
+            warnings.push(f());
+        }
+    }
 }
 
 // We parse with this super-duper-dinky hand-coded recursive descent parser because we don't really
@@ -74,50 +569,87 @@ macro_rules! take_item {
     }};
 }
 
-type ParseResult<T> = Result<T, ParseErr>;
+type ParseResult<T> = Result<T, Failure>;
 
 macro_rules! take_char {
     ($e: expr, $c:expr) => {{
         $e = match $e.chars().next() {
             Some($c) => &$e[1..],
             Some(_) => {
-                return Err(ParseErr::ExpectedTokenErr($c));
+                return Err(Failure::at(ParseErrKind::ExpectedTokenErr($c), $e));
+            }
+            None => {
+                return Err(Failure::at(ParseErrKind::UnexpectedEndOfInput, $e));
+            }
+        }
+    }};
+}
+
+macro_rules! take_separator {
+    ($e: expr, $opts:expr) => {{
+        $e = match $e.chars().next() {
+            Some(' ') => &$e[1..],
+            Some('\r') if $opts.allow_cr_before_separator() && $e[1..].starts_with(' ') => &$e[2..],
+            Some(c) => {
+                return Err(Failure::at(ParseErrKind::BadSeparator(c), $e));
             }
             None => {
-                return Err(ParseErr::UnexpectedEndOfInput);
+                return Err(Failure::at(ParseErrKind::UnexpectedEndOfInput, $e));
             }
         }
     }};
 }
 
-fn take_while<F>(input: &str, f: F, max_chars: usize) -> (&str, Option<&str>)
+fn take_while<F>(input: &str, f: F, max_chars: usize) -> (&str, &str)
 where
     F: Fn(char) -> bool,
 {
     for (idx, chr) in input.char_indices() {
         if !f(chr) {
-            return (&input[..idx], Some(&input[idx..]));
+            return (&input[..idx], &input[idx..]);
         }
         if idx == max_chars {
-            return (&input[..idx], Some(&input[idx..]));
+            return (&input[..idx], &input[idx..]);
         }
     }
-    ("", None)
+    (input, "")
 }
 
-fn parse_sd_id(input: &str) -> ParseResult<(String, &str)> {
+fn parse_sd_id(input: &str) -> ParseResult<(&str, &str)> {
     let (res, rest) = take_while(input, |c| c != ' ' && c != '=' && c != ']', 128);
-    Ok((
-        String::from(res),
-        match rest {
-            Some(s) => s,
-            None => return Err(ParseErr::UnexpectedEndOfInput),
-        },
-    ))
+    if rest.is_empty() {
+        return Err(Failure::at(ParseErrKind::UnexpectedEndOfInput, rest));
+    }
+    Ok((res, rest))
+}
+
+fn is_sd_name_char(c: char) -> bool {
+    c.is_ascii_graphic() && !matches!(c, '=' | ']' | '"' | '@')
+}
+
+/// Whether `name` is a valid SD-NAME, as required of param names
+fn is_valid_sd_name(name: &str) -> bool {
+    (1..=32).contains(&name.len()) && name.chars().all(is_sd_name_char)
+}
+
+/// Whether `id` is a valid SD-ID: an SD-NAME, or `name@number` with a private enterprise number
+fn is_valid_sd_id(id: &str) -> bool {
+    match id.split_once('@') {
+        Some((name, number)) => {
+            id.len() <= 32
+                && is_valid_sd_name(name)
+                && !number.contains('@')
+                && message::enterprise_number(id).is_some()
+        }
+        None => is_valid_sd_name(id),
+    }
 }
 
 /** Parse a `param_value`... a.k.a. a quoted string */
-fn parse_param_value(input: &str) -> ParseResult<(Cow<str>, &str)> {
+fn parse_param_value<'a>(
+    input: &'a str,
+    ctx: &mut ParseContext,
+) -> ParseResult<(Cow<'a, str>, &'a str)> {
     let mut rest = input;
     take_char!(rest, '"');
     // Can't do a 0-copy &str slice here because we need to un-escape escaped quotes
@@ -128,47 +660,91 @@ fn parse_param_value(input: &str) -> ParseResult<(Cow<str>, &str)> {
     let mut escaped = false;
 
     for (idx, chr) in rest.char_indices() {
+        ctx.step(1, &rest[idx..])?;
         if escaped {
-            escaped = false
-        } else {
-            if chr == '\\' {
-                escaped = true;
-                if !saw_any_escapes {
-                    result.push_str(&rest[..idx]);
+            escaped = false;
+            // RFC 5424 only defines escapes for these three characters; a backslash before
+            // anything else is to be treated as a literal backslash.
+            match chr {
+                '"' | '\\' | ']' => result.push(chr),
+                'n' if ctx.opts.allow_c_escapes() => result.push('\n'),
+                't' if ctx.opts.allow_c_escapes() => result.push('\t'),
+                'r' if ctx.opts.allow_c_escapes() => result.push('\r'),
+                _ => {
+                    result.push('\\');
+                    result.push(chr);
                 }
-                saw_any_escapes = true;
-                continue;
             }
-            if chr == '"' {
-                let res_cow = if saw_any_escapes {
-                    Cow::Owned(result)
-                } else {
-                    Cow::Borrowed(&rest[..idx])
-                };
-                return Ok((res_cow, &rest[(idx + 1)..]));
+            continue;
+        }
+        if chr == '\\' {
+            escaped = true;
+            if !saw_any_escapes {
+                result.push_str(&rest[..idx]);
             }
+            saw_any_escapes = true;
+            continue;
+        }
+        if chr == '"' {
+            let res_cow = if saw_any_escapes {
+                Cow::Owned(result)
+            } else {
+                Cow::Borrowed(&rest[..idx])
+            };
+            return Ok((res_cow, &rest[(idx + 1)..]));
         }
         if saw_any_escapes {
             result.push(chr);
         }
     }
 
-    Err(ParseErr::UnexpectedEndOfInput)
+    Err(Failure::at(ParseErrKind::UnexpectedEndOfInput, ""))
 }
 
-type ParsedSDParams = Vec<(String, String)>;
+type ParsedSDParams<'a> = Vec<(&'a str, Cow<'a, str>)>;
 
-fn parse_sd_params(input: &str) -> ParseResult<(ParsedSDParams, &str)> {
+fn parse_sd_params<'a>(
+    input: &'a str,
+    ctx: &mut ParseContext,
+) -> ParseResult<(ParsedSDParams<'a>, &'a str)> {
     let mut params = Vec::new();
     let mut top = input;
     loop {
         if let Some(rest2) = maybe_expect_char!(top, ' ') {
             let mut rest = rest2;
-            let param_name = take_item!(parse_sd_id(rest), rest);
-            take_char!(rest, '=');
-            let param_value = take_item!(parse_param_value(rest), rest);
-            // is there an uglier modifier than &*
-            params.push((param_name, String::from(&*param_value)));
+            let mut param_name = take_item!(parse_sd_id(rest), rest);
+            ctx.step(param_name.len() + 2, rest)?;
+            let colon = if ctx.opts.allow_colon_sd_separator() {
+                param_name.find(":\"")
+            } else {
+                None
+            };
+            let bare = if let Some(idx) = colon {
+                // the name ran on into the value, so the colon was the separator
+                param_name = &param_name[..idx];
+                rest = &rest2[idx + 1..];
+                false
+            } else if ctx.opts.allow_bare_sd_params()
+                && (rest.starts_with(' ') || rest.starts_with(']'))
+            {
+                true
+            } else {
+                take_char!(rest, '=');
+                false
+            };
+            if ctx.opts.strict_sd_names() && !is_valid_sd_name(param_name) {
+                return Err(Failure::at(
+                    ParseErrKind::InvalidSdName(String::from(param_name)),
+                    rest2,
+                ));
+            }
+            if bare {
+                params.push((param_name, Cow::Borrowed("")));
+                top = rest;
+                continue;
+            }
+            let param_value = take_item!(parse_param_value(rest, ctx), rest);
+            params.push((param_name, param_value));
             top = rest;
         } else {
             return Ok((params, top));
@@ -176,70 +752,184 @@ fn parse_sd_params(input: &str) -> ParseResult<(ParsedSDParams, &str)> {
     }
 }
 
-fn parse_sde(sde: &str) -> ParseResult<((String, ParsedSDParams), &str)> {
+fn parse_sde<'a>(
+    sde: &'a str,
+    ctx: &mut ParseContext,
+) -> ParseResult<((&'a str, ParsedSDParams<'a>), &'a str)> {
     let mut rest = sde;
     take_char!(rest, '[');
+    let id_start = rest;
     let id = take_item!(parse_sd_id(rest), rest);
-    let params = take_item!(parse_sd_params(rest), rest);
+    if ctx.opts.strict_sd_names() && !is_valid_sd_id(id) {
+        return Err(Failure::at(
+            ParseErrKind::InvalidSdName(String::from(id)),
+            id_start,
+        ));
+    }
+    ctx.step(id.len() + 2, rest)?;
+    let params = take_item!(parse_sd_params(rest, ctx), rest);
     take_char!(rest, ']');
     Ok(((id, params), rest))
 }
 
-fn parse_sd(structured_data_raw: &str) -> ParseResult<(StructuredData, &str)> {
-    let mut sd = StructuredData::new_empty();
+/// Where `parse_sd` puts what it finds: any `StructuredDataMap`, which copies it, a
+/// `StructuredDataRef`, which borrows from the input, or a `ParseHandler`, which keeps nothing
+pub(crate) trait SdSink<'a> {
+    fn add_element(&mut self, sd_id: &'a str, ctx: &mut ParseContext);
+
+    fn contains_param(&self, sd_id: &str, sd_param_id: &str) -> bool;
+
+    fn add_param(
+        &mut self,
+        sd_id: &'a str,
+        sd_param_id: Cow<'a, str>,
+        sd_param_value: Cow<'a, str>,
+        ctx: &mut ParseContext,
+    );
+}
+
+impl<'a, M: StructuredDataMap> SdSink<'a> for M {
+    fn add_element(&mut self, sd_id: &'a str, ctx: &mut ParseContext) {
+        self.add_element_owned(ctx.sd_name(sd_id))
+    }
+
+    fn contains_param(&self, sd_id: &str, sd_param_id: &str) -> bool {
+        StructuredDataMap::contains_param(self, sd_id, sd_param_id)
+    }
+
+    fn add_param(
+        &mut self,
+        sd_id: &'a str,
+        sd_param_id: Cow<'a, str>,
+        sd_param_value: Cow<'a, str>,
+        ctx: &mut ParseContext,
+    ) {
+        StructuredDataMap::add_param(
+            self,
+            sd_id,
+            ctx.sd_name(&sd_param_id),
+            sd_param_value.into_owned(),
+        )
+    }
+}
+
+fn parse_sd<'a, M: SdSink<'a>>(
+    structured_data_raw: &'a str,
+    sd: &mut M,
+    ctx: &mut ParseContext,
+) -> ParseResult<&'a str> {
     if let Some(rest) = structured_data_raw.strip_prefix('-') {
-        return Ok((sd, rest));
+        return Ok(rest);
     }
     let mut rest = structured_data_raw;
     while !rest.is_empty() {
-        let (sd_id, params) = take_item!(parse_sde(rest), rest);
-        let sub_map = sd.entry(sd_id.clone());
+        let (sd_id, params) = take_item!(parse_sde(rest, ctx), rest);
+        sd.add_element(sd_id, ctx);
         for (sd_param_id, sd_param_value) in params {
-            sub_map.insert(sd_param_id, sd_param_value);
+            if sd.contains_param(sd_id, sd_param_id) {
+                ctx.warn(|| ParseWarning::DuplicateSdParam {
+                    sd_id: String::from(sd_id),
+                    param: String::from(sd_param_id),
+                });
+            }
+            sd.add_param(sd_id, Cow::Borrowed(sd_param_id), sd_param_value, ctx);
         }
-        if rest.starts_with(' ') {
+        // a CR-contaminated separator is dealt with by the caller
+        if rest.starts_with(' ') || rest.starts_with("\r ") {
             break;
         }
     }
-    Ok((sd, rest))
+    Ok(rest)
+}
+
+/// Split a raw PRI value into its facility and severity
+///
+/// This is for priorities read from somewhere other than a full RFC 5424 message; the bits are
+/// decoded exactly as the parser decodes the `<PRI>` of a message.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::{from_priority, SyslogFacility, SyslogSeverity};
+///
+/// assert_eq!(
+///     from_priority(165).unwrap(),
+///     (SyslogFacility::LOG_LOCAL4, SyslogSeverity::SEV_NOTICE)
+/// );
+/// assert!(from_priority(192).is_err());
+/// ```
+pub fn from_priority(
+    pri: i32,
+) -> Result<(facility::SyslogFacility, severity::SyslogSeverity), ParseErr> {
+    match parse_pri_val(pri, &ParseOptions::default()) {
+        Ok((sev, fac)) => Ok((fac, sev)),
+        Err(kind) => Err(ParseErr { kind, offset: 0 }),
+    }
 }
 
-fn parse_pri_val(pri: i32) -> ParseResult<(severity::SyslogSeverity, facility::SyslogFacility)> {
-    let sev = severity::SyslogSeverity::from_int(pri & 0x7).ok_or(ParseErr::BadSeverityInPri)?;
-    let fac = facility::SyslogFacility::from_int(pri >> 3).ok_or(ParseErr::BadFacilityInPri)?;
+fn parse_pri_val(
+    pri: i32,
+    opts: &ParseOptions,
+) -> Result<(severity::SyslogSeverity, facility::SyslogFacility), ParseErrKind> {
+    if pri == 0 && opts.pri_zero_as_default() {
+        return Ok((
+            opts.default_severity()
+                .unwrap_or(severity::SyslogSeverity::SEV_NOTICE),
+            opts.default_facility()
+                .unwrap_or(facility::SyslogFacility::LOG_USER),
+        ));
+    }
+    let (sev, fac) = if opts.swapped_pri() {
+        (pri >> 3, pri & 0x7)
+    } else {
+        (pri & 0x7, pri >> 3)
+    };
+    let sev = severity::SyslogSeverity::from_int(sev).ok_or(ParseErrKind::BadSeverityInPri)?;
+    let fac = facility::SyslogFacility::from_int(fac).ok_or(ParseErrKind::BadFacilityInPri)?;
     Ok((sev, fac))
 }
 
 /// Parse an i32
 fn parse_num(s: &str, min_digits: usize, max_digits: usize) -> ParseResult<(i32, &str)> {
-    let (res, rest1) = take_while(s, |c| ('0'..='9').contains(&c), max_digits);
-    let rest = rest1.ok_or(ParseErr::UnexpectedEndOfInput)?;
+    let (res, rest) = take_while(s, |c: char| c.is_ascii_digit(), max_digits);
     if res.len() < min_digits {
-        Err(ParseErr::TooFewDigits)
+        Err(Failure::expected(CharClass::Digit, rest))
     } else if res.len() > max_digits {
-        Err(ParseErr::TooManyDigits)
+        Err(Failure::at(ParseErrKind::TooManyDigits, rest))
     } else {
-        Ok((
-            i32::from_str(res).map_err(ParseErr::IntConversionErr)?,
-            rest,
-        ))
+        let n =
+            i32::from_str(res).map_err(|e| Failure::at(ParseErrKind::IntConversionErr(e), s))?;
+        Ok((n, rest))
     }
 }
 
-/// Parse an i32
-fn parse_num_generic<NT>(s: &str, min_digits: usize, max_digits: usize) -> ParseResult<(NT, &str)>
-where
-    NT: FromStr<Err = num::ParseIntError>,
-{
-    let (res, rest1) = take_while(s, |c| ('0'..='9').contains(&c), max_digits);
-    let rest = rest1.ok_or(ParseErr::UnexpectedEndOfInput)?;
-    if res.len() < min_digits {
-        Err(ParseErr::TooFewDigits)
-    } else if res.len() > max_digits {
-        Err(ParseErr::TooManyDigits)
-    } else {
-        Ok((NT::from_str(res).map_err(ParseErr::IntConversionErr)?, rest))
+/// Parse exactly `n` (at most 9) ASCII digits by direct accumulation
+///
+/// This is for the fixed-width timestamp fields, where the generality of `from_str` (signs,
+/// overflow checks) is wasted work on the hot path.
+fn parse_fixed_digits(s: &str, n: usize) -> ParseResult<(u32, &str)> {
+    let bytes = s.as_bytes();
+    let mut val = 0u32;
+    for idx in 0..n {
+        let b = match bytes.get(idx) {
+            Some(b) if b.is_ascii_digit() => b,
+            // a non-digit is either ASCII or the start of a multi-byte character, so `idx` is
+            // always a char boundary here
+            _ => return Err(Failure::expected(CharClass::Digit, &s[idx..])),
+        };
+        val = val * 10 + u32::from(b - b'0');
+    }
+    Ok((val, &s[n..]))
+}
+
+/// Parse a two-digit timestamp field, or just one digit with `allow_unpadded_timestamp_fields`
+fn parse_timestamp_field<'a>(s: &'a str, opts: &ParseOptions) -> ParseResult<(u32, &'a str)> {
+    if opts.allow_unpadded_timestamp_fields()
+        && s.bytes().take(2).take_while(u8::is_ascii_digit).count() == 1
+    {
+        return parse_fixed_digits(s, 1);
     }
+    parse_fixed_digits(s, 2)
 }
 
 fn parse_decimal(d: &str, min_digits: usize, max_digits: usize) -> ParseResult<(i32, &str)> {
@@ -254,170 +944,1394 @@ fn parse_decimal(d: &str, min_digits: usize, max_digits: usize) -> ParseResult<(
     })
 }
 
-fn parse_timestamp(m: &str) -> ParseResult<(Option<time::OffsetDateTime>, &str)> {
+/// Parse TIME-SECFRAC (after the `.`) into nanoseconds, also returning whether rounding carried
+/// over into a whole second
+fn parse_fractional_seconds<'a>(
+    s: &'a str,
+    opts: &ParseOptions,
+) -> ParseResult<(u32, bool, &'a str)> {
+    let max_digits = match opts.max_frac_digits() {
+        Some(max_digits) => max_digits.min(9),
+        None if opts.allow_long_fractional_seconds() => 9,
+        None => {
+            let (nanos, rest) = parse_decimal(s, 1, 6)?;
+            return Ok((nanos as u32, false, rest));
+        }
+    };
+    let (digits, rest) = take_while(s, |c: char| c.is_ascii_digit(), usize::MAX);
+    if digits.is_empty() {
+        return Err(Failure::expected(CharClass::Digit, s));
+    }
+    let (kept, extra) = digits.split_at(digits.len().min(max_digits));
+    // the value of one unit in the last kept digit
+    let unit = 10u32.pow(9 - kept.len() as u32);
+    let mut nanos = kept.bytes().fold(0, |n, d| n * 10 + u32::from(d - b'0')) * unit;
+    if opts.fractional_rounding() == Rounding::HalfUp && extra.as_bytes().first() >= Some(&b'5') {
+        nanos += unit;
+    }
+    if nanos == 1_000_000_000 {
+        Ok((0, true, rest))
+    } else {
+        Ok((nanos, false, rest))
+    }
+}
+
+/// The result of parsing a non-NILVALUE TIMESTAMP
+struct ParsedTimestamp {
+    datetime: time::OffsetDateTime,
+    /// Whether the offset was given as `-00:00`, which RFC 3339 uses to mean "UTC, but the local
+    /// offset is unknown"
+    offset_unknown: bool,
+    /// The offset the timestamp was given in, in seconds east of UTC
+    offset_secs: i32,
+    /// Whether the seconds were `60`, for a leap second
+    leap_second: bool,
+}
+
+fn parse_timestamp<'a>(
+    m: &'a str,
+    opts: &ParseOptions,
+) -> ParseResult<(Option<ParsedTimestamp>, &'a str)> {
     let mut rest = m;
     if let Some(rest) = rest.strip_prefix('-') {
         return Ok((None, rest));
     }
-    let year = take_item!(parse_num(rest, 4, 4), rest);
+    if opts.allow_epoch_timestamp() {
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        if (9..=10).contains(&digits) && !rest[digits..].starts_with(|c: char| c != ' ') {
+            let secs = i64::from_str(&rest[..digits])
+                .map_err(|e| Failure::at(ParseErrKind::IntConversionErr(e), m))?;
+            let datetime = time::OffsetDateTime::from_unix_timestamp(secs)
+                .map_err(|e| Failure::at(ParseErrKind::InvalidDate(e.name().to_string()), m))?;
+            return Ok((
+                Some(ParsedTimestamp {
+                    datetime,
+                    offset_unknown: false,
+                    offset_secs: 0,
+                    leap_second: false,
+                }),
+                &rest[digits..],
+            ));
+        }
+    }
+    // every field is at most 4 digits, so the narrowing casts below can't truncate
+    let year = take_item!(parse_fixed_digits(rest, 4), rest) as i32;
     take_char!(rest, '-');
-    let month_num = take_item!(parse_num_generic(rest, 2, 2), rest);
-    let month = time::Month::try_from(month_num).map_err(|_| ParseErr::InvalidMonth(month_num))?;
+    let month_num = take_item!(parse_timestamp_field(rest, opts), rest) as u8;
+    let month = time::Month::try_from(month_num)
+        .map_err(|_| Failure::at(ParseErrKind::InvalidMonth(month_num), m))?;
     take_char!(rest, '-');
-    let mday = take_item!(parse_num_generic(rest, 2, 2), rest);
+    let mday = take_item!(parse_timestamp_field(rest, opts), rest) as u8;
     let date = time::Date::from_calendar_date(year, month, mday)
-        .map_err(|e| ParseErr::InvalidDate(e.name().to_string()))?;
-    take_char!(rest, 'T');
-    let hour = take_item!(parse_num_generic(rest, 2, 2), rest);
+        .map_err(|e| Failure::at(ParseErrKind::InvalidDate(e.name().to_string()), m))?;
+    if (opts.allow_space_time_separator() && rest.starts_with(' '))
+        || (opts.allow_lowercase_t_and_z() && rest.starts_with('t'))
+    {
+        rest = &rest[1..];
+    } else {
+        take_char!(rest, 'T');
+    }
+    let hour = take_item!(parse_timestamp_field(rest, opts), rest) as u8;
     take_char!(rest, ':');
-    let minute = take_item!(parse_num_generic(rest, 2, 2), rest);
+    let minute = take_item!(parse_timestamp_field(rest, opts), rest) as u8;
     take_char!(rest, ':');
-    let second = take_item!(parse_num_generic(rest, 2, 2), rest);
-    let nano = if rest.starts_with('.') {
+    let mut second = take_item!(parse_timestamp_field(rest, opts), rest) as u8;
+    let (mut nano, mut carry) = if rest.starts_with('.') {
         take_char!(rest, '.');
-        take_item!(parse_decimal(rest, 1, 6), rest) as u32
+        if opts.allow_empty_fraction() && !rest.starts_with(|c: char| c.is_ascii_digit()) {
+            // a `-` standing in for the fraction, as opposed to the sign of a numeric offset
+            if rest.starts_with('-') && !rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+                rest = &rest[1..];
+            }
+            (0, false)
+        } else {
+            let (nano, carry, r) = parse_fractional_seconds(rest, opts)?;
+            rest = r;
+            (nano, carry)
+        }
     } else {
-        0
+        (0, false)
     };
+    // RFC 3339 allows a seconds value of 60 for a positive leap second, which Unix time has no
+    // room for; count it as the last microsecond of second 59 instead, so that it still sorts
+    // after everything else in that minute, and is written back within RFC 5424's six
+    // TIME-SECFRAC digits
+    let leap_second = second == 60;
+    if leap_second {
+        second = 59;
+        nano = 999_999_000;
+        carry = false;
+    }
     let time = time::Time::from_hms_nano(hour, minute, second, nano)
-        .map_err(|e| ParseErr::InvalidDate(e.name().to_string()))?;
-    // Tm::utcoff is totally broken, don't use it.
+        .map_err(|e| Failure::at(ParseErrKind::InvalidDate(e.name().to_string()), m))?;
+    let mut offset_unknown = false;
     let utc_offset = match rest.chars().next() {
         None => None,
         Some('Z') => {
             rest = &rest[1..];
             None
         }
+        Some('z') if opts.allow_lowercase_t_and_z() => {
+            rest = &rest[1..];
+            None
+        }
         Some(c) => {
             let (sign, irest) = match c {
                 // Note: signs are backwards as per RFC3339
                 '-' => (-1, &rest[1..]),
                 '+' => (1, &rest[1..]),
                 _ => {
-                    return Err(ParseErr::InvalidUTCOffset);
+                    return Err(Failure::at(ParseErrKind::InvalidUTCOffset, rest));
                 }
             };
-            let hours = i8::from_str(&irest[0..2]).map_err(ParseErr::IntConversionErr)?;
-            let minutes = i8::from_str(&irest[3..5]).map_err(ParseErr::IntConversionErr)?;
-            rest = &irest[5..];
+            let mut irest = irest;
+            let hours = take_item!(parse_fixed_digits(irest, 2), irest) as i8;
+            take_char!(irest, ':');
+            let minutes = take_item!(parse_fixed_digits(irest, 2), irest) as i8;
+            rest = irest;
+            offset_unknown = sign == -1 && hours == 0 && minutes == 0;
             Some(
                 time::UtcOffset::from_hms(hours * sign, minutes * sign, 0)
-                    .map_err(|_| ParseErr::InvalidOffset)?,
+                    .map_err(|_| Failure::at(ParseErrKind::InvalidOffset, rest))?,
             )
         }
     };
+    let offset_secs = utc_offset.map_or(0, |o| o.whole_seconds());
     let naive_dt = time::PrimitiveDateTime::new(date, time);
-    let dt = if let Some(utc_offset) = utc_offset {
+    let mut dt = if let Some(utc_offset) = utc_offset {
         naive_dt.assume_offset(utc_offset)
     } else {
         naive_dt.assume_utc()
     };
-    Ok((Some(dt), rest))
+    if carry {
+        dt = dt.checked_add(time::Duration::SECOND).ok_or_else(|| {
+            Failure::at(ParseErrKind::InvalidDate(String::from("out of range")), m)
+        })?;
+    }
+    Ok((
+        Some(ParsedTimestamp {
+            datetime: dt,
+            offset_unknown,
+            offset_secs,
+            leap_second,
+        }),
+        rest,
+    ))
 }
 
-fn parse_term(
-    m: &str,
+/// Parse a header field of `min_length` to `max_length` printable characters, or the NILVALUE
+///
+/// `name` is what a `ParseErrKind::FieldTooLong` calls the field.
+fn parse_term<'a>(
+    m: &'a str,
+    name: &'static str,
     min_length: usize,
     max_length: usize,
-) -> ParseResult<(Option<String>, &str)> {
+    ctx: &mut ParseContext,
+) -> ParseResult<(Option<&'a str>, &'a str)> {
     if m.starts_with('-') && (m.len() <= 1 || m.as_bytes()[1] == 0x20) {
         return Ok((None, &m[1..]));
     }
-    let byte_ary = m.as_bytes();
-    for (idx, chr) in byte_ary.iter().enumerate() {
-        if *chr < 33 || *chr > 126 {
-            if idx < min_length {
-                return Err(ParseErr::TooFewDigits);
+    // Lengths are counted in characters so that, when UTF-8 is allowed, we never split a
+    // multi-byte character; for the RFC's printable-ASCII fields this is the same as bytes.
+    for (count, (idx, chr)) in m.char_indices().enumerate() {
+        ctx.step(1, &m[idx..])?;
+        let printable = if chr.is_ascii() {
+            ('!'..='~').contains(&chr)
+        } else {
+            ctx.opts.allow_utf8_in_header() && !chr.is_whitespace() && !chr.is_control()
+        };
+        if !printable {
+            if count < min_length {
+                return Err(Failure::expected(CharClass::Printable, &m[idx..]));
             }
-            let utf8_ary = str::from_utf8(&byte_ary[..idx]).map_err(ParseErr::BaseUnicodeError)?;
-            return Ok((Some(String::from(utf8_ary)), &m[idx..]));
+            let term = &m[..idx];
+            if term == "-" {
+                return Ok((None, &m[idx..]));
+            }
+            return Ok((Some(term), &m[idx..]));
+        }
+        if count >= max_length {
+            return Err(Failure::at(ParseErrKind::FieldTooLong(name), &m[idx..]));
+        }
+    }
+    Err(Failure::at(ParseErrKind::UnexpectedEndOfInput, ""))
+}
+
+/// Everything before the MSG, borrowing from the input where possible
+struct Header<'a, M = StructuredData> {
+    severity: severity::SyslogSeverity,
+    facility: facility::SyslogFacility,
+    version: i32,
+    timestamp: Option<ParsedTimestamp>,
+    hostname: Option<&'a str>,
+    appname: Option<&'a str>,
+    procid: Option<&'a str>,
+    msgid: Option<&'a str>,
+    sd: M,
+}
+
+/// The SD-ID that `ParseOptions::collect_extra_header_fields` files extra fields under
+const EXTRA_FIELDS_SD_ID: &str = "_extra";
+
+/// Split off the space-separated fields ahead of the structured data, for
+/// `ParseOptions::collect_extra_header_fields`, or none at all if no structured data follows
+fn take_extra_fields(s: &str) -> (Vec<&str>, &str) {
+    let mut fields = Vec::new();
+    let mut rest = s;
+    while !rest.starts_with('[') {
+        let end = rest.find(' ').unwrap_or(rest.len());
+        let field = &rest[..end];
+        if field.trim_end_matches('\r') == "-" {
+            break;
         }
-        if idx >= max_length {
-            let utf8_ary = str::from_utf8(&byte_ary[..idx]).map_err(ParseErr::BaseUnicodeError)?;
-            return Ok((Some(String::from(utf8_ary)), &m[idx..]));
+        if field.is_empty() || end == rest.len() {
+            return (Vec::new(), s);
         }
+        fields.push(field);
+        rest = &rest[end + 1..];
     }
-    Err(ParseErr::UnexpectedEndOfInput)
+    (fields, rest)
 }
 
-fn parse_message_s(m: &str) -> ParseResult<SyslogMessage> {
+/// Parse everything up to and including the separator before the MSG, returning the header and
+/// the MSG
+fn parse_header<'a, M: SdSink<'a> + Default>(
+    m: &'a str,
+    ctx: &mut ParseContext,
+) -> ParseResult<(Header<'a, M>, &'a str)> {
+    let (fields, rest) = parse_header_fields(m, ctx)?;
+    let mut sd = M::default();
+    let rest = parse_header_sd(rest, &mut sd, ctx)?;
+    Ok((
+        Header {
+            severity: fields.severity,
+            facility: fields.facility,
+            version: fields.version,
+            timestamp: fields.timestamp,
+            hostname: fields.hostname,
+            appname: fields.appname,
+            procid: fields.procid,
+            msgid: fields.msgid,
+            sd,
+        },
+        rest,
+    ))
+}
+
+/// Parse the header up to and including the separator after the MSGID, returning the
+/// structured data and everything after it
+fn parse_header_fields<'a>(
+    m: &'a str,
+    ctx: &mut ParseContext,
+) -> ParseResult<(Header<'a, ()>, &'a str)> {
+    let opts = ctx.opts;
     let mut rest = m;
     take_char!(rest, '<');
+    let pri_start = rest;
     let prival = take_item!(parse_num(rest, 1, 3), rest);
-    take_char!(rest, '>');
-    let (sev, fac) = parse_pri_val(prival)?;
-    let version = take_item!(parse_num(rest, 1, 2), rest);
-    take_char!(rest, ' ');
-    let event_time = take_item!(parse_timestamp(rest), rest);
-    take_char!(rest, ' ');
-    let hostname = take_item!(parse_term(rest, 1, 255), rest);
-    take_char!(rest, ' ');
-    let appname = take_item!(parse_term(rest, 1, 48), rest);
-    take_char!(rest, ' ');
-    let procid = take_item!(parse_term(rest, 1, 128), rest).map(|s| match i32::from_str(&s) {
-        Ok(n) => ProcId::PID(n),
-        Err(_) => ProcId::Name(s),
-    });
-    take_char!(rest, ' ');
-    let msgid = take_item!(parse_term(rest, 1, 32), rest);
-    take_char!(rest, ' ');
-    let sd = take_item!(parse_sd(rest), rest);
-    rest = match maybe_expect_char!(rest, ' ') {
-        Some(r) => r,
-        None => rest,
+    let (sev, fac) = if opts.allow_dotted_pri() && rest.starts_with('.') {
+        rest = &rest[1..];
+        let sev_start = rest;
+        let sev = take_item!(parse_num(rest, 1, 2), rest);
+        take_char!(rest, '>');
+        let fac = facility::SyslogFacility::from_int(prival)
+            .ok_or_else(|| Failure::at(ParseErrKind::BadFacilityInPri, pri_start))?;
+        let sev = severity::SyslogSeverity::from_int(sev)
+            .ok_or_else(|| Failure::at(ParseErrKind::BadSeverityInPri, sev_start))?;
+        (sev, fac)
+    } else {
+        take_char!(rest, '>');
+        parse_pri_val(prival, opts).map_err(|kind| Failure::at(kind, pri_start))?
     };
-    let msg = String::from(rest);
+    if opts.allow_space_before_version() && rest.starts_with(' ') {
+        rest = rest.trim_start_matches(' ');
+        // make sure that what follows is a version, not a header with the version left out
+        // entirely (such as `<13> 2016-01-15T00:04:01Z ...`)
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 || digits > 2 {
+            return Err(Failure::at(ParseErrKind::MissingField("version"), rest));
+        }
+    }
+    let version = take_item!(parse_num(rest, 1, 2), rest);
+    // A message that stops right after the version (with or without the
+    // separator) is missing its timestamp; a wrong separator character is
+    // still reported as `BadSeparator` by the macro below.
+    if rest.is_empty() {
+        return Err(Failure::at(ParseErrKind::MissingField("timestamp"), rest));
+    }
+    take_separator!(rest, opts);
+    if rest.is_empty() {
+        return Err(Failure::at(ParseErrKind::MissingField("timestamp"), rest));
+    }
+    let event_time = take_item!(parse_timestamp(rest, opts), rest);
+    if event_time.as_ref().is_some_and(|t| t.leap_second) {
+        ctx.warn(|| ParseWarning::LeapSecond);
+    }
+    ctx.step(m.len() - rest.len(), rest)?;
+    take_separator!(rest, opts);
+    let hostname = take_item!(parse_term(rest, "hostname", 1, 255, ctx), rest);
+    take_separator!(rest, opts);
+    let appname = take_item!(parse_term(rest, "appname", 1, 48, ctx), rest);
+    take_separator!(rest, opts);
+    let procid = take_item!(parse_term(rest, "procid", 1, 128, ctx), rest);
+    take_separator!(rest, opts);
+    let msgid = take_item!(parse_term(rest, "msgid", 1, 32, ctx), rest);
+    take_separator!(rest, opts);
+    Ok((
+        Header {
+            severity: sev,
+            facility: fac,
+            version,
+            timestamp: event_time,
+            hostname,
+            appname,
+            procid,
+            msgid,
+            sd: (),
+        },
+        rest,
+    ))
+}
 
-    Ok(SyslogMessage {
-        severity: sev,
-        facility: fac,
-        version,
-        timestamp: event_time.map(|t| t.unix_timestamp()),
-        timestamp_nanos: event_time.map(|t| t.time().nanosecond()),
-        hostname,
-        appname,
-        procid,
-        msgid,
-        sd,
-        msg,
-    })
+/// Parse the STRUCTURED-DATA into `sd`, along with the separator before the MSG if there is one,
+/// returning the MSG
+fn parse_header_sd<'a, M: SdSink<'a>>(
+    m: &'a str,
+    sd: &mut M,
+    ctx: &mut ParseContext,
+) -> ParseResult<&'a str> {
+    let opts = ctx.opts;
+    let mut rest = m;
+    let extra_fields = if opts.collect_extra_header_fields() {
+        let (fields, r) = take_extra_fields(rest);
+        ctx.step(rest.len() - r.len(), r)?;
+        rest = r;
+        fields
+    } else {
+        Vec::new()
+    };
+    if !(opts.allow_missing_sd() && !rest.is_empty() && !rest.starts_with(['[', '-'])) {
+        rest = parse_sd(rest, sd, ctx)?;
+    }
+    if !extra_fields.is_empty() {
+        sd.add_element(EXTRA_FIELDS_SD_ID, ctx);
+        for (idx, field) in extra_fields.into_iter().enumerate() {
+            sd.add_param(
+                EXTRA_FIELDS_SD_ID,
+                Cow::Owned((idx + 1).to_string()),
+                Cow::Borrowed(field),
+                ctx,
+            );
+        }
+    }
+    if rest.starts_with("\r ") {
+        if !opts.allow_cr_before_separator() {
+            return Err(Failure::at(ParseErrKind::BadSeparator('\r'), rest));
+        }
+        rest = &rest[2..];
+    } else if let Some(r) = maybe_expect_char!(rest, ' ') {
+        rest = r;
+    }
+    Ok(rest)
 }
 
-/// Parse a string into a `SyslogMessage` object
-///
-/// # Arguments
-///
-///  * `s`: Anything convertible to a string
-///
-/// # Returns
-///
-///  * `ParseErr` if the string is not parseable as an RFC5424 message
-///
-/// # Example
-///
-/// ```
-/// use syslog_rfc5424::parse_message;
-///
-/// let message = parse_message("<78>1 2016-01-15T00:04:01+00:00 host1 CROND 10391 - [meta sequenceId=\"29\"] some_message").unwrap();
-///
-/// assert!(message.hostname.unwrap() == "host1");
-/// ```
-pub fn parse_message<S: AsRef<str>>(s: S) -> ParseResult<SyslogMessage> {
-    parse_message_s(s.as_ref())
+/// Decode `%XX` escapes, returning the offset of the first malformed one on error
+fn percent_decode(b: &[u8]) -> Result<Vec<u8>, usize> {
+    let mut out = Vec::with_capacity(b.len());
+    let mut idx = 0;
+    while idx < b.len() {
+        if b[idx] == b'%' {
+            let byte = b
+                .get(idx + 1..idx + 3)
+                .and_then(|hex| str::from_utf8(hex).ok())
+                .filter(|hex| hex.bytes().all(|c| c.is_ascii_hexdigit()))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or(idx)?;
+            out.push(byte);
+            idx += 3;
+        } else {
+            out.push(b[idx]);
+            idx += 1;
+        }
+    }
+    Ok(out)
 }
 
-#[cfg(test)]
-mod tests {
-    use std::collections::BTreeMap;
-    use std::mem;
+/// Undo `ParseOptions::input_encoding`, if any
+fn decode_input<'a>(b: &'a [u8], opts: &ParseOptions) -> Result<Cow<'a, [u8]>, ParseErr> {
+    let fail = |name, offset| ParseErr {
+        kind: ParseErrKind::InvalidInputEncoding(name),
+        offset,
+    };
+    match opts.input_encoding {
+        InputEncoding::None => Ok(Cow::Borrowed(b)),
+        #[cfg(feature = "base64")]
+        InputEncoding::Base64 => {
+            use base64::engine::{general_purpose, DecodePaddingMode, GeneralPurpose};
+            use base64::{alphabet, DecodeError, Engine};
 
-    use super::{parse_message, ParseErr};
-    use crate::message;
+            const ENGINE: GeneralPurpose = GeneralPurpose::new(
+                &alphabet::STANDARD,
+                general_purpose::PAD.with_decode_padding_mode(DecodePaddingMode::Indifferent),
+            );
+            ENGINE.decode(b).map(Cow::Owned).map_err(|e| {
+                let offset = match e {
+                    DecodeError::InvalidByte(offset, _)
+                    | DecodeError::InvalidLastSymbol(offset, _) => offset,
+                    _ => b.len(),
+                };
+                fail("base64", offset)
+            })
+        }
+        InputEncoding::Percent => percent_decode(b)
+            .map(Cow::Owned)
+            .map_err(|offset| fail("percent-encoding", offset)),
+    }
+}
 
-    use crate::facility::SyslogFacility;
-    use crate::severity::SyslogSeverity;
+/// `decode_input` for input which must also decode to UTF-8
+fn decode_input_str<'a>(s: &'a str, opts: &ParseOptions) -> Result<Cow<'a, str>, ParseErr> {
+    match decode_input(s.as_bytes(), opts)? {
+        Cow::Borrowed(_) => Ok(Cow::Borrowed(s)),
+        Cow::Owned(decoded) => String::from_utf8(decoded)
+            .map(Cow::Owned)
+            .map_err(|e| ParseErr {
+                offset: e.utf8_error().valid_up_to(),
+                kind: e.into(),
+            }),
+    }
+}
 
-    #[test]
+/// Parse a whole message, returning its header along with the MSG and trailer
+fn parse_message_parts<'a, M: SdSink<'a> + Default>(
+    m: &'a str,
+    ctx: &mut ParseContext,
+) -> ParseResult<(Header<'a, M>, Body<'a>)> {
+    let (header, rest) = parse_header(m, ctx)?;
+    let body = parse_body(m, rest, ctx)?;
+    Ok((header, body))
+}
+
+/// Everything after the header
+struct Body<'a> {
+    msg: &'a str,
+    /// Whether the MSG started with a BOM, which isn't included in `msg`
+    has_bom: bool,
+    trailer: Option<&'a str>,
+}
+
+/// Split what follows the header of `m` into the MSG and trailer
+fn parse_body<'a>(m: &'a str, rest: &'a str, ctx: &mut ParseContext) -> ParseResult<Body<'a>> {
+    let opts = ctx.opts;
+    ctx.step(rest.len(), rest)?;
+    let (rest, has_bom) = match rest.strip_prefix('\u{feff}') {
+        Some(r) => (r, true),
+        None => (rest, false),
+    };
+    ctx.msg_offset = m.len() - rest.len();
+    let (msg, trailer) = match opts
+        .trailing_field_delimiter()
+        .and_then(|d| rest.rfind(d).map(|idx| (idx, d)))
+    {
+        Some((idx, d)) => (&rest[..idx], Some(&rest[idx + d.len_utf8()..])),
+        None => (rest, None),
+    };
+    if msg.ends_with(|c: char| c.is_ascii_whitespace()) {
+        ctx.warn(|| ParseWarning::TrailingWhitespace);
+    }
+    Ok(Body {
+        msg,
+        has_bom,
+        trailer,
+    })
+}
+
+pub(crate) fn parse_procid(s: &str) -> ProcId {
+    match i32::from_str(s) {
+        Ok(n) => ProcId::PID(n),
+        Err(_) => ProcId::Name(String::from(s)),
+    }
+}
+
+fn parse_message_s<M: StructuredDataMap>(
+    m: &str,
+    ctx: &mut ParseContext,
+) -> ParseResult<SyslogMessage<M>> {
+    let (header, body) = parse_message_parts(m, ctx)?;
+
+    let event_time = header.timestamp;
+    Ok(SyslogMessage {
+        severity: header.severity,
+        facility: header.facility,
+        version: header.version,
+        timestamp: event_time.as_ref().map(|t| t.datetime.unix_timestamp()),
+        timestamp_nanos: event_time.as_ref().map(|t| t.datetime.nanosecond()),
+        timestamp_offset_unknown: event_time.as_ref().is_some_and(|t| t.offset_unknown),
+        timestamp_offset_secs: event_time.as_ref().map(|t| t.offset_secs),
+        hostname: header.hostname.map(|s| ctx.name(s)),
+        appname: header.appname.map(|s| ctx.name(s)),
+        procid: header.procid.map(parse_procid),
+        msgid: header.msgid.map(String::from),
+        sd: header.sd,
+        msg: String::from(body.msg),
+        msg_has_bom: body.has_bom,
+        msg_raw: None,
+        trailer: body.trailer.map(String::from),
+    })
+}
+
+fn parse_message_ref_s<'a>(
+    m: &'a str,
+    ctx: &mut ParseContext,
+) -> ParseResult<SyslogMessageRef<'a>> {
+    let (header, body) = parse_message_parts(m, ctx)?;
+
+    let event_time = header.timestamp;
+    Ok(SyslogMessageRef {
+        severity: header.severity,
+        facility: header.facility,
+        version: header.version,
+        timestamp: event_time.as_ref().map(|t| t.datetime.unix_timestamp()),
+        timestamp_nanos: event_time.as_ref().map(|t| t.datetime.nanosecond()),
+        timestamp_offset_unknown: event_time.as_ref().is_some_and(|t| t.offset_unknown),
+        timestamp_offset_secs: event_time.as_ref().map(|t| t.offset_secs),
+        hostname: header.hostname.map(Cow::Borrowed),
+        appname: header.appname.map(Cow::Borrowed),
+        procid: header.procid.map(Cow::Borrowed),
+        msgid: header.msgid.map(Cow::Borrowed),
+        sd: header.sd,
+        msg: Cow::Borrowed(body.msg),
+        msg_has_bom: body.has_bom,
+        trailer: body.trailer.map(Cow::Borrowed),
+    })
+}
+
+/// Overwrite `dst` with `src`, reusing the buffer already in `dst` if there is one
+fn assign_string(dst: &mut Option<String>, src: Option<&str>) {
+    if let (Some(buf), Some(s)) = (dst.as_mut(), src) {
+        buf.clear();
+        buf.push_str(s);
+    } else {
+        *dst = src.map(String::from);
+    }
+}
+
+fn parse_into_s(m: &str, out: &mut SyslogMessage, ctx: &mut ParseContext) -> ParseResult<()> {
+    let (header, body) = match parse_message_parts(m, ctx) {
+        Ok(parts) => parts,
+        Err(e) => {
+            out.clear();
+            return Err(e);
+        }
+    };
+
+    let event_time = header.timestamp;
+    out.severity = header.severity;
+    out.facility = header.facility;
+    out.version = header.version;
+    out.timestamp = event_time.as_ref().map(|t| t.datetime.unix_timestamp());
+    out.timestamp_nanos = event_time.as_ref().map(|t| t.datetime.nanosecond());
+    out.timestamp_offset_unknown = event_time.as_ref().is_some_and(|t| t.offset_unknown);
+    out.timestamp_offset_secs = event_time.as_ref().map(|t| t.offset_secs);
+    out.hostname = header.hostname.map(|s| ctx.name(s));
+    out.appname = header.appname.map(|s| ctx.name(s));
+    match (out.procid.as_mut(), header.procid) {
+        (Some(ProcId::Name(buf)), Some(s)) if i32::from_str(s).is_err() => {
+            buf.clear();
+            buf.push_str(s);
+        }
+        (_, procid) => out.procid = procid.map(parse_procid),
+    }
+    assign_string(&mut out.msgid, header.msgid);
+    out.sd = header.sd;
+    out.msg.clear();
+    out.msg.push_str(body.msg);
+    out.msg_has_bom = body.has_bom;
+    out.msg_raw = None;
+    assign_string(&mut out.trailer, body.trailer);
+    Ok(())
+}
+
+/// Parse a string into a `SyslogMessage` object
+///
+/// # Arguments
+///
+///  * `s`: Anything convertible to a string
+///
+/// # Returns
+///
+///  * `ParseErr` if the string is not parseable as an RFC5424 message
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::parse_message;
+///
+/// let message = parse_message("<78>1 2016-01-15T00:04:01+00:00 host1 CROND 10391 - [meta sequenceId=\"29\"] some_message").unwrap();
+///
+/// assert_eq!(message.hostname.as_deref(), Some("host1"));
+/// ```
+pub fn parse_message<S: AsRef<str>>(s: S) -> Result<SyslogMessage, ParseErr> {
+    let s = s.as_ref();
+    parse_message_s(s, &mut ParseContext::new(&ParseOptions::default())).map_err(|f| f.locate(s))
+}
+
+/// Parse a string into a `SyslogMessage` object, rejecting SD-IDs and param names which aren't
+/// valid per RFC 5424
+///
+/// This is `parse_message` with `ParseOptions::strict_sd_names` set.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::{parse_message_strict, ParseErrKind};
+///
+/// let err = parse_message_strict(r#"<78>1 - - - - - [me"ta x="1"] -"#).unwrap_err();
+///
+/// assert!(matches!(err.kind(), ParseErrKind::InvalidSdName(_)));
+/// assert!(parse_message_strict(r#"<78>1 - - - - - [meta@32473 x="1"] -"#).is_ok());
+/// ```
+pub fn parse_message_strict<S: AsRef<str>>(s: S) -> Result<SyslogMessage, ParseErr> {
+    #[cfg(not(feature = "strict-only"))]
+    let opts = ParseOptions {
+        strict_sd_names: true,
+        ..ParseOptions::default()
+    };
+    #[cfg(feature = "strict-only")]
+    let opts = ParseOptions::default();
+    parse_message_with_options(s, &opts)
+}
+
+/// Parse a string into a `SyslogMessage` object, accepting the deviations from RFC 5424 listed
+/// under `ParseOptions::lenient`
+///
+/// This is `parse_message_with_options` with `ParseOptions::lenient()`. A message which
+/// `parse_message` accepts is parsed the same way by this.
+pub fn parse_message_lenient<S: AsRef<str>>(s: S) -> Result<SyslogMessage, ParseErr> {
+    parse_message_with_options(s, &ParseOptions::lenient())
+}
+
+/// Parse a string into a `SyslogMessage` object, using the given `ParseOptions`
+///
+/// With `ParseOptions::default()`, this behaves exactly like `parse_message`.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::{parse_message_with_options, ParseOptions};
+/// # #[cfg(feature = "strict-only")]
+/// # fn main() {}
+/// # #[cfg(not(feature = "strict-only"))]
+/// # fn main() {
+/// let opts = ParseOptions {
+///     allow_utf8_in_header: true,
+///     ..Default::default()
+/// };
+/// let message = parse_message_with_options("<78>1 - hôst1 - - - - some_message", &opts).unwrap();
+///
+/// assert_eq!(message.hostname.as_deref(), Some("hôst1"));
+/// # }
+/// ```
+pub fn parse_message_with_options<S: AsRef<str>>(
+    s: S,
+    opts: &ParseOptions,
+) -> Result<SyslogMessage, ParseErr> {
+    let s = decode_input_str(s.as_ref(), opts)?;
+    parse_message_s(&s, &mut ParseContext::new(opts)).map_err(|f| f.locate(&s))
+}
+
+/// Parse a string into a `SyslogMessageRef`, which borrows its fields from `s` instead of
+/// copying them
+///
+/// This does the same work as `parse_message` but without allocating a `String` per field; call
+/// `SyslogMessageRef::to_owned` to get a `SyslogMessage` out of it.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::parse_message_ref;
+///
+/// let line = String::from(r#"<78>1 - host1 CROND 10391 - [meta sequenceId="29"] some_message"#);
+/// let message = parse_message_ref(&line).unwrap();
+///
+/// assert_eq!(message.hostname.as_deref(), Some("host1"));
+/// assert_eq!(message.sd.find_tuple("meta", "sequenceId"), Some("29"));
+/// ```
+pub fn parse_message_ref(s: &str) -> Result<SyslogMessageRef<'_>, ParseErr> {
+    parse_message_ref_s(s, &mut ParseContext::new(&ParseOptions::default()))
+        .map_err(|f| f.locate(s))
+}
+
+/// Parse a string into a `SyslogMessageRef`, using the given `ParseOptions`
+///
+/// If `ParseOptions::input_encoding` means the input has to be decoded first, nothing can be
+/// borrowed from it, and every field of the result is owned instead.
+pub fn parse_message_ref_with_options<'a>(
+    s: &'a str,
+    opts: &ParseOptions,
+) -> Result<SyslogMessageRef<'a>, ParseErr> {
+    let mut ctx = ParseContext::new(opts);
+    match decode_input_str(s, opts)? {
+        Cow::Borrowed(s) => parse_message_ref_s(s, &mut ctx).map_err(|f| f.locate(s)),
+        Cow::Owned(decoded) => parse_message_ref_s(&decoded, &mut ctx)
+            .map(SyslogMessageRef::into_static)
+            .map_err(|f| f.locate(&decoded)),
+    }
+}
+
+/// Parse a string into a `SyslogMessage` whose structured data is collected into `M`, using the
+/// given `ParseOptions`
+///
+/// `parse_message_with::<StructuredData>` is the same as `parse_message_with_options`; the other
+/// provided container is `message::MultiStructuredData`, which keeps every value of a repeated
+/// param rather than only the last one. Such messages can't be used with the methods which
+/// expect a `StructuredData`, such as `Display`.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::message::MultiStructuredData;
+/// use syslog_rfc5424::{parse_message_with, ParseOptions};
+///
+/// let msg = parse_message_with::<MultiStructuredData>(
+///     r#"<78>1 - host1 - - - [tags t="a" t="b"] message"#,
+///     &ParseOptions::default(),
+/// ).unwrap();
+///
+/// assert_eq!(msg.hostname.as_deref(), Some("host1"));
+/// assert_eq!(msg.sd.find_all_tuples("tags", "t"), ["a", "b"]);
+/// ```
+pub fn parse_message_with<M: StructuredDataMap>(
+    s: &str,
+    opts: &ParseOptions,
+) -> Result<SyslogMessage<M>, ParseErr> {
+    let s = decode_input_str(s, opts)?;
+    parse_message_s(&s, &mut ParseContext::new(opts)).map_err(|f| f.locate(&s))
+}
+
+/// Parse a string into an existing `SyslogMessage`, reusing its allocations
+///
+/// This is to `parse_message_with_options` what `BufRead::read_line` is to reading into a new
+/// `String`: when parsing many messages in a loop, passing the same `out` every time saves
+/// allocating fresh buffers for each message. Every field of `out` is overwritten, but the
+/// buffers behind `msg`, `msgid`, `trailer`, and a named `procid` are reused when both the old and
+/// new values are present. (The structured data is a `BTreeMap`, which has no spare capacity to
+/// keep, so it's simply replaced.)
+///
+/// If parsing fails, `out` is left cleared, as by `SyslogMessage::clear`.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::{parse_into, parse_message, ParseOptions};
+///
+/// let opts = ParseOptions::default();
+/// let mut msg = parse_message("<78>1 - - - - - - placeholder").unwrap();
+/// for line in &["<78>1 - host1 - - - - first", "<78>1 - host2 - - - - second"] {
+///     parse_into(line, &mut msg, &opts).unwrap();
+///     println!("{:?}: {}", msg.hostname, msg.msg);
+/// }
+/// assert_eq!(msg.msg, "second");
+/// ```
+pub fn parse_into<S: AsRef<str>>(
+    s: S,
+    out: &mut SyslogMessage,
+    opts: &ParseOptions,
+) -> Result<(), ParseErr> {
+    let s = match decode_input_str(s.as_ref(), opts) {
+        Ok(s) => s,
+        Err(e) => {
+            out.clear();
+            return Err(e);
+        }
+    };
+    parse_into_s(&s, out, &mut ParseContext::new(opts)).map_err(|f| f.locate(&s))
+}
+
+/// Parse a string into a `SyslogMessage` object, also returning any `ParseWarning`s
+///
+/// Warnings describe input that was accepted but may have lost information, such as a repeated
+/// SD param whose earlier values were overwritten.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::{parse_message_with_warnings, ParseOptions, ParseWarning};
+///
+/// let (message, warnings) = parse_message_with_warnings(
+///     "<78>1 - - - - - [meta x=\"1\" x=\"2\"] some_message",
+///     &ParseOptions::default(),
+/// ).unwrap();
+///
+/// assert_eq!(message.sd.find_tuple("meta", "x").unwrap(), "2");
+/// assert_eq!(
+///     warnings,
+///     vec![ParseWarning::DuplicateSdParam { sd_id: "meta".into(), param: "x".into() }]
+/// );
+/// ```
+pub fn parse_message_with_warnings<S: AsRef<str>>(
+    s: S,
+    opts: &ParseOptions,
+) -> Result<(SyslogMessage, Vec<ParseWarning>), ParseErr> {
+    let s = decode_input_str(s.as_ref(), opts)?;
+    let mut ctx = ParseContext::new(opts);
+    ctx.warnings = Some(Vec::new());
+    let msg = parse_message_s(&s, &mut ctx).map_err(|f| f.locate(&s))?;
+    Ok((msg, ctx.warnings.unwrap_or_default()))
+}
+
+/// Parse the first message out of a buffer of LF-terminated messages, also returning the number
+/// of bytes it occupied
+///
+/// The message runs up to the first LF (or the end of the input if there is none), leaving out a
+/// CR just before the LF; the count includes the CR and LF, so it can be used directly to advance
+/// past the message.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::{parse_message_consumed, ParseOptions};
+///
+/// let buf = "<78>1 - host1 - - - - first\n<78>1 - host2 - - - - second\n";
+/// let opts = ParseOptions::default();
+///
+/// let (first, consumed) = parse_message_consumed(buf, &opts).unwrap();
+/// assert_eq!(first.msg, "first");
+/// let (second, _) = parse_message_consumed(&buf[consumed..], &opts).unwrap();
+/// assert_eq!(second.msg, "second");
+/// ```
+pub fn parse_message_consumed<S: AsRef<str>>(
+    s: S,
+    opts: &ParseOptions,
+) -> Result<(SyslogMessage, usize), ParseErr> {
+    let s = s.as_ref();
+    let (line, consumed) = match s.find('\n') {
+        Some(idx) => {
+            let line = &s[..idx];
+            (line.strip_suffix('\r').unwrap_or(line), idx + 1)
+        }
+        None => (s, s.len()),
+    };
+    let line = decode_input_str(line, opts)?;
+    let msg = parse_message_s(&line, &mut ParseContext::new(opts)).map_err(|f| f.locate(&line))?;
+    Ok((msg, consumed))
+}
+
+/// Parse one RFC 6587 octet-counted frame (`MSG-LEN SP SYSLOG-MSG`) off the front of a buffer,
+/// returning the message and the rest of the buffer
+///
+/// Exactly `MSG-LEN` bytes after the space are parsed as the message. If the buffer holds fewer
+/// bytes than that, the error is `ParseErrKind::UnexpectedEndOfInput`, and more data should be
+/// read before trying again; a missing or malformed length is `ParseErrKind::InvalidOctetCount`.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::parse_octet_counted;
+///
+/// let buf = "16 <1>1 - - - - - -27 <1>1 - host2 - - - - second";
+///
+/// let (first, rest) = parse_octet_counted(buf).unwrap();
+/// assert!(first.hostname.is_none());
+/// let (second, rest) = parse_octet_counted(rest).unwrap();
+/// assert_eq!(second.msg, "second");
+/// assert_eq!(rest, "");
+/// ```
+pub fn parse_octet_counted(input: &str) -> Result<(SyslogMessage, &str), ParseErr> {
+    parse_octet_counted_with_options(input, &ParseOptions::default())
+}
+
+/// Parse one RFC 6587 octet-counted frame off the front of a buffer, using the given
+/// `ParseOptions`; see `parse_octet_counted`
+pub fn parse_octet_counted_with_options<'a>(
+    input: &'a str,
+    opts: &ParseOptions,
+) -> Result<(SyslogMessage, &'a str), ParseErr> {
+    let (start, end) = split_octet_counted(input)?;
+    let add_start = |mut err: ParseErr| {
+        err.offset += start;
+        err
+    };
+    let frame = decode_input_str(&input[start..end], opts).map_err(add_start)?;
+    let msg = parse_message_s(&frame, &mut ParseContext::new(opts))
+        .map_err(|f| add_start(f.locate(&frame)))?;
+    Ok((msg, &input[end..]))
+}
+
+/// Find the message in the octet-counted frame at the front of `input`, returning its start and
+/// end offsets
+///
+/// An incomplete frame is `ParseErrKind::UnexpectedEndOfInput`; nothing else can return that.
+fn split_octet_counted(input: &str) -> Result<(usize, usize), ParseErr> {
+    let invalid = |offset| ParseErr {
+        kind: ParseErrKind::InvalidOctetCount,
+        offset,
+    };
+    let digits = input.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 || input.starts_with('0') {
+        return Err(invalid(0));
+    }
+    match input.as_bytes().get(digits) {
+        Some(b' ') => {}
+        Some(_) => return Err(invalid(digits)),
+        None => {
+            return Err(ParseErr {
+                kind: ParseErrKind::UnexpectedEndOfInput,
+                offset: input.len(),
+            })
+        }
+    }
+    let len = usize::from_str(&input[..digits]).map_err(|_| invalid(0))?;
+    let start = digits + 1;
+    let end = start.checked_add(len).ok_or_else(|| invalid(0))?;
+    if end > input.len() {
+        return Err(ParseErr {
+            kind: ParseErrKind::UnexpectedEndOfInput,
+            offset: input.len(),
+        });
+    }
+    // a count which splits a character can't be right
+    if !input.is_char_boundary(end) {
+        return Err(invalid(0));
+    }
+    Ok((start, end))
+}
+
+/// Parse every complete RFC 6587 octet-counted frame in a buffer, returning the messages and
+/// how many bytes of the buffer they took up
+///
+/// This is for buffers read off a stream, which may end partway through a frame: parsing stops
+/// cleanly before an incomplete final frame, which should be kept and have the next read
+/// appended to it. A frame which is complete but can't be parsed (or whose length is malformed)
+/// also stops parsing, and the error is returned along with everything parsed before it, its
+/// offset counting from the start of the buffer; the consumed length is then where the bad
+/// frame starts. There is no telling where the next frame starts after a bad length, so the
+/// connection is usually best given up on at that point.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::parse_messages;
+///
+/// let buf = "16 <1>1 - - - - - -22 <1>1 - - - - - - hello27 <1>1 - host2";
+///
+/// let (messages, consumed, err) = parse_messages(buf);
+/// assert_eq!(messages.len(), 2);
+/// assert_eq!(messages[1].msg, "hello");
+/// assert_eq!(&buf[consumed..], "27 <1>1 - host2");
+/// assert!(err.is_none());
+/// ```
+pub fn parse_messages(input: &str) -> (Vec<SyslogMessage>, usize, Option<ParseErr>) {
+    parse_messages_with_options(input, &ParseOptions::default())
+}
+
+/// Parse every complete octet-counted frame in a buffer, using the given `ParseOptions`; see
+/// `parse_messages`
+pub fn parse_messages_with_options(
+    input: &str,
+    opts: &ParseOptions,
+) -> (Vec<SyslogMessage>, usize, Option<ParseErr>) {
+    let mut messages = Vec::new();
+    let mut consumed = 0;
+    while consumed < input.len() {
+        let rest = &input[consumed..];
+        let err = match split_octet_counted(rest) {
+            Ok(_) => match parse_octet_counted_with_options(rest, opts) {
+                Ok((msg, r)) => {
+                    messages.push(msg);
+                    consumed = input.len() - r.len();
+                    continue;
+                }
+                Err(err) => err,
+            },
+            Err(err) if matches!(err.kind, ParseErrKind::UnexpectedEndOfInput) => break,
+            Err(err) => err,
+        };
+        let err = ParseErr {
+            offset: err.offset + consumed,
+            ..err
+        };
+        return (messages, consumed, Some(err));
+    }
+    (messages, consumed, None)
+}
+
+/// Parse a string as a syslog message, but only return its `StructuredData`
+///
+/// The whole header is still validated, but nothing outside of the structured data is copied
+/// out of the input, which makes this cheaper than `parse_message` for consumers that only care
+/// about the SD.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::parse_structured_data_only;
+///
+/// let sd = parse_structured_data_only("<78>1 - host1 - - - [meta sequenceId=\"29\"] a long message").unwrap();
+///
+/// assert_eq!(sd.find_tuple("meta", "sequenceId").unwrap(), "29");
+/// ```
+pub fn parse_structured_data_only<S: AsRef<str>>(s: S) -> Result<StructuredData, ParseErr> {
+    let s = s.as_ref();
+    let (header, _) = parse_header(s, &mut ParseContext::new(&ParseOptions::default()))
+        .map_err(|f| f.locate(s))?;
+    Ok(header.sd)
+}
+
+/// Callbacks for `parse_sax`, called in the order the fields appear in the message
+///
+/// Every method does nothing by default, so only the ones of interest need implementing.
+#[allow(unused_variables)]
+pub trait ParseHandler {
+    /// The facility and severity from the PRI
+    fn on_priority(
+        &mut self,
+        facility: facility::SyslogFacility,
+        severity: severity::SyslogSeverity,
+    ) {
+    }
+
+    /// The TIMESTAMP, in the offset it was given in, or `None` if it was `-`
+    fn on_timestamp(&mut self, timestamp: Option<time::OffsetDateTime>) {}
+
+    /// The HOSTNAME, or `None` if it was `-`
+    fn on_hostname(&mut self, hostname: Option<&str>) {}
+
+    /// The APP-NAME, or `None` if it was `-`
+    fn on_appname(&mut self, appname: Option<&str>) {}
+
+    /// The PROCID, or `None` if it was `-`
+    fn on_procid(&mut self, procid: Option<&str>) {}
+
+    /// The MSGID, or `None` if it was `-`
+    fn on_msgid(&mut self, msgid: Option<&str>) {}
+
+    /// The start of an SD element; its params (if any) follow
+    fn on_sd_element(&mut self, sd_id: &str) {}
+
+    /// One SD param, with escapes in the value already undone. Repeated params are reported
+    /// every time they appear.
+    fn on_sd_param(&mut self, sd_id: &str, name: &str, value: &str) {}
+
+    /// The MSG, which may be empty
+    fn on_message(&mut self, msg: &str) {}
+}
+
+/// Feeds structured data straight into a `ParseHandler`
+struct HandlerSink<'h, H>(&'h mut H);
+
+impl<'a, H: ParseHandler> SdSink<'a> for HandlerSink<'_, H> {
+    fn add_element(&mut self, sd_id: &'a str, _ctx: &mut ParseContext) {
+        self.0.on_sd_element(sd_id);
+    }
+
+    fn contains_param(&self, _sd_id: &str, _sd_param_id: &str) -> bool {
+        false
+    }
+
+    fn add_param(
+        &mut self,
+        sd_id: &'a str,
+        sd_param_id: Cow<'a, str>,
+        sd_param_value: Cow<'a, str>,
+        _ctx: &mut ParseContext,
+    ) {
+        self.0.on_sd_param(sd_id, &sd_param_id, &sd_param_value);
+    }
+}
+
+fn parse_sax_s<H: ParseHandler>(
+    m: &str,
+    handler: &mut H,
+    ctx: &mut ParseContext,
+) -> ParseResult<()> {
+    let (fields, rest) = parse_header_fields(m, ctx)?;
+    handler.on_priority(fields.facility, fields.severity);
+    handler.on_timestamp(fields.timestamp.map(|t| t.datetime));
+    handler.on_hostname(fields.hostname);
+    handler.on_appname(fields.appname);
+    handler.on_procid(fields.procid);
+    handler.on_msgid(fields.msgid);
+    let rest = parse_header_sd(rest, &mut HandlerSink(handler), ctx)?;
+    let body = parse_body(m, rest, ctx)?;
+    handler.on_message(body.msg);
+    Ok(())
+}
+
+/// Parse a string, reporting each field to `handler` as it is parsed rather than collecting
+/// them into a `SyslogMessage`
+///
+/// Nothing is copied out of the input except SD param values with escapes in them, which makes
+/// this the cheapest way to consume messages that don't need to be kept. The header fields
+/// (PRI through MSGID) are only reported once all of them have parsed, so an error in any of them
+/// reports nothing. After that, SD elements and params are reported as they are parsed, so if
+/// the structured data or MSG fails, `handler` will already have seen everything before the
+/// error.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::{parse_sax, ParseHandler};
+///
+/// #[derive(Default)]
+/// struct CountParams(usize);
+///
+/// impl ParseHandler for CountParams {
+///     fn on_sd_param(&mut self, _sd_id: &str, _name: &str, _value: &str) {
+///         self.0 += 1;
+///     }
+/// }
+///
+/// let mut counter = CountParams::default();
+/// parse_sax(r#"<78>1 - host1 - - - [meta sequenceId="29"][origin ip="192.0.2.1"] hi"#, &mut counter).unwrap();
+///
+/// assert_eq!(counter.0, 2);
+/// ```
+pub fn parse_sax<H: ParseHandler>(s: &str, handler: &mut H) -> Result<(), ParseErr> {
+    parse_sax_s(s, handler, &mut ParseContext::new(&ParseOptions::default()))
+        .map_err(|f| f.locate(s))
+}
+
+/// `parse_sax`, using the given `ParseOptions`
+///
+/// A trailer split off by `ParseOptions::trailing_field_delimiter` is not reported.
+pub fn parse_sax_with_options<H: ParseHandler>(
+    s: &str,
+    handler: &mut H,
+    opts: &ParseOptions,
+) -> Result<(), ParseErr> {
+    let s = decode_input_str(s, opts)?;
+    parse_sax_s(&s, handler, &mut ParseContext::new(opts)).map_err(|f| f.locate(&s))
+}
+
+/// Parse raw bytes into a `SyslogMessage` object, using the given `ParseOptions`
+///
+/// Whether the bytes must be entirely valid UTF-8 is controlled by `ParseOptions::utf8_mode`.
+/// Invalid UTF-8 in the header or structured data is always rejected with
+/// `ParseErrKind::BaseUnicodeError`; in `Utf8Mode::LossyMsg`, invalid sequences in the MSG body are
+/// replaced with U+FFFD instead, and the original bytes are kept in `msg_raw`. If a message containing invalid UTF-8 fails to parse at all,
+/// the UTF-8 error is the one reported, since it is the likely culprit.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::{parse_message_bytes_with_options, ParseOptions, Utf8Mode};
+///
+/// let raw = b"<78>1 - host1 - - - - caf\xe9";
+/// assert!(parse_message_bytes_with_options(raw, &ParseOptions::default()).is_err());
+///
+/// let opts = ParseOptions {
+///     utf8_mode: Utf8Mode::LossyMsg,
+///     ..Default::default()
+/// };
+/// let message = parse_message_bytes_with_options(raw, &opts).unwrap();
+/// assert_eq!(message.msg, "caf\u{fffd}");
+/// assert_eq!(message.msg_raw.as_deref(), Some(&b"caf\xe9"[..]));
+/// ```
+pub fn parse_message_bytes_with_options(
+    b: &[u8],
+    opts: &ParseOptions,
+) -> Result<SyslogMessage, ParseErr> {
+    let b = decode_input(b, opts)?;
+    let b = &b[..];
+    let utf8_err = match str::from_utf8(b) {
+        Ok(s) => return parse_message_s(s, &mut ParseContext::new(opts)).map_err(|f| f.locate(s)),
+        Err(e) => e,
+    };
+    let utf8_failure = ParseErr {
+        offset: utf8_err.valid_up_to(),
+        kind: utf8_err.into(),
+    };
+    if opts.utf8_mode == Utf8Mode::Strict {
+        return Err(utf8_failure);
+    }
+    parse_lossy(b, utf8_failure, opts)
+}
+
+/// Parse `b`, which isn't valid UTF-8, replacing invalid sequences in the MSG with U+FFFD and
+/// keeping the original bytes of the MSG in `msg_raw`
+///
+/// If anything before the MSG is invalid, the error is `utf8_failure`.
+fn parse_lossy(
+    b: &[u8],
+    utf8_failure: ParseErr,
+    opts: &ParseOptions,
+) -> Result<SyslogMessage, ParseErr> {
+    let lossy = String::from_utf8_lossy(b);
+    let mut ctx = ParseContext::new(opts);
+    match parse_message_s(&lossy, &mut ctx) {
+        // everything before the first invalid sequence is unchanged by the lossy conversion, so
+        // offsets into `lossy` up to that point are also offsets into `b`
+        Ok(mut msg) if ctx.msg_offset <= utf8_failure.offset => {
+            msg.msg_raw = Some(b[ctx.msg_offset..].to_vec());
+            Ok(msg)
+        }
+        _ => Err(utf8_failure),
+    }
+}
+
+/// Parse raw bytes into a `SyslogMessage`, allowing the MSG to be arbitrary binary data
+///
+/// RFC 5424 allows the MSG to be any sequence of octets, but the header and structured data have
+/// to be text. If the MSG isn't valid UTF-8, `msg` holds a lossy conversion of it (with invalid
+/// sequences replaced by U+FFFD) and `msg_raw` holds the original bytes; otherwise this is the
+/// same as `parse_message`. Invalid UTF-8 before the MSG is an error.
+///
+/// This is `parse_message_bytes_with_options` with `Utf8Mode::LossyMsg`.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::parse_message_bytes;
+///
+/// let message = parse_message_bytes(b"<78>1 - host1 - - - - \x00\xff\xfe").unwrap();
+///
+/// assert_eq!(message.hostname.as_deref(), Some("host1"));
+/// assert_eq!(message.msg, "\0\u{fffd}\u{fffd}");
+/// assert_eq!(message.msg_raw.as_deref(), Some(&b"\x00\xff\xfe"[..]));
+/// ```
+pub fn parse_message_bytes(b: &[u8]) -> Result<SyslogMessage, ParseErr> {
+    let opts = ParseOptions {
+        utf8_mode: Utf8Mode::LossyMsg,
+        ..Default::default()
+    };
+    parse_message_bytes_with_options(b, &opts)
+}
+
+/// The outcome of validating one line of a file of syslog messages
+///
+/// With the `serde-serialize` feature this serializes to `{"line_number":…,"ok":…,"error":…}`,
+/// which is handy for emitting a JSON-lines report.
+#[cfg_attr(feature = "serde-serialize", derive(Serialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineReport {
+    pub line_number: usize,
+    pub ok: bool,
+    /// The `ParseErr` message, if the line failed to parse
+    pub error: Option<String>,
+}
+
+/// Check whether `line` parses as a syslog message, describing the result as a `LineReport`
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::validate_line_report;
+///
+/// assert!(validate_line_report(1, "<1>1 - - - - - -").ok);
+///
+/// let report = validate_line_report(2, "<1>1");
+/// assert!(!report.ok);
+/// assert_eq!(report.line_number, 2);
+/// assert!(report.error.is_some());
+/// ```
+pub fn validate_line_report(line_no: usize, line: &str) -> LineReport {
+    let error = parse_message(line).err().map(|e| e.to_string());
+    LineReport {
+        line_number: line_no,
+        ok: error.is_none(),
+        error,
+    }
+}
+
+/// A reusable parser which interns the low-cardinality HOSTNAME and APP-NAME fields
+///
+/// Collectors tend to see the same few hundred hosts and applications over and over; rather than
+/// allocating a fresh string for each message, a `Parser` hands out shared `Arc<str>`s from a
+/// least-recently-used cache holding at most `max_interned` distinct values.
+///
+/// Only available with the `interning` feature, which also changes `SyslogMessage::hostname` and
+/// `SyslogMessage::appname` to hold `Arc<str>`.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use syslog_rfc5424::{ParseOptions, Parser};
+///
+/// let mut parser = Parser::new(ParseOptions::default(), 1024);
+/// let first = parser.parse("<78>1 - host1 CROND - - - one").unwrap();
+/// let second = parser.parse("<78>1 - host1 CROND - - - two").unwrap();
+///
+/// assert!(Arc::ptr_eq(&first.hostname.unwrap(), &second.hostname.unwrap()));
+/// ```
+#[cfg(feature = "interning")]
+#[derive(Debug)]
+pub struct Parser {
+    opts: ParseOptions,
+    interner: Interner,
+    #[cfg(feature = "sd-interning")]
+    sd_interner: Interner,
+}
+
+#[cfg(feature = "interning")]
+impl Parser {
+    /// Create a parser using `opts`, remembering at most `max_interned` distinct names
+    ///
+    /// With the `sd-interning` feature, SD-IDs and param names are remembered in a separate
+    /// table of the same size, so that a feed with many hosts doesn't crowd them out.
+    pub fn new(opts: ParseOptions, max_interned: usize) -> Self {
+        Parser {
+            opts,
+            interner: Interner::new(max_interned),
+            #[cfg(feature = "sd-interning")]
+            sd_interner: Interner::new(max_interned),
+        }
+    }
+
+    /// The options this parser was created with
+    pub fn options(&self) -> &ParseOptions {
+        &self.opts
+    }
+
+    /// The number of distinct names currently interned
+    pub fn interned_len(&self) -> usize {
+        self.interner.len()
+    }
+
+    /// The number of distinct SD-IDs and param names currently interned
+    #[cfg(feature = "sd-interning")]
+    pub fn sd_interned_len(&self) -> usize {
+        self.sd_interner.len()
+    }
+
+    /// Parse a string into a `SyslogMessage` object, as `parse_message_with_options` would
+    pub fn parse<S: AsRef<str>>(&mut self, s: S) -> Result<SyslogMessage, ParseErr> {
+        let s = decode_input_str(s.as_ref(), &self.opts)?;
+        let mut ctx = ParseContext::new(&self.opts);
+        ctx.interner = Some(&mut self.interner);
+        #[cfg(feature = "sd-interning")]
+        {
+            ctx.sd_interner = Some(&mut self.sd_interner);
+        }
+        parse_message_s(&s, &mut ctx).map_err(|f| f.locate(&s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::mem;
+
+    use super::{
+        from_priority, parse_message, parse_message_bytes, parse_message_bytes_with_options,
+        parse_message_consumed, parse_message_lenient, parse_message_ref, parse_message_strict,
+        parse_message_with, parse_message_with_options, parse_message_with_warnings,
+        parse_messages, parse_octet_counted, parse_sax, parse_structured_data_only, CharClass,
+        InputEncoding, ParseErr, ParseErrKind, ParseHandler, ParseOptions, ParseWarning, Utf8Mode,
+    };
+    #[cfg(not(feature = "strict-only"))]
+    use super::{parse_into, Rounding};
+    use crate::borrowed::StructuredDataRef;
+    #[cfg(not(feature = "strict-only"))]
+    use crate::message::ProcId;
+    use crate::message::{self, MultiStructuredData, StructuredData};
+
+    use crate::facility::SyslogFacility;
+    use crate::severity::SyslogSeverity;
+
+    #[test]
     fn test_simple() {
         let msg = parse_message("<1>1 - - - - - -").expect("Should parse empty message");
         assert!(msg.facility == SyslogFacility::LOG_KERN);
@@ -427,214 +2341,1904 @@ mod tests {
         assert!(msg.appname.is_none());
         assert!(msg.procid.is_none());
         assert!(msg.msgid.is_none());
-        assert!(msg.sd.len() == 0);
+        assert!(msg.sd.is_empty());
+    }
+
+    #[test]
+    fn test_with_time_zulu() {
+        let msg = parse_message("<1>1 2015-01-01T00:00:00Z host - - - -")
+            .expect("Should parse empty message");
+        assert_eq!(msg.timestamp, Some(1420070400));
+    }
+
+    #[test]
+    fn test_with_time_offset() {
+        let msg = parse_message("<1>1 2015-01-01T00:00:00+00:00 - - - - -")
+            .expect("Should parse empty message");
+        assert_eq!(msg.timestamp, Some(1420070400));
+    }
+
+    #[test]
+    fn test_with_time_offset_nonzero() {
+        let msg = parse_message("<1>1 2015-01-01T00:00:00-10:00 - - - - -")
+            .expect("Should parse empty message");
+        assert_eq!(msg.timestamp, Some(1420106400));
+        // example from RFC 3339
+        let msg1 = parse_message("<1>1 2015-01-01T18:50:00-04:00 - - - - -")
+            .expect("Should parse empty message");
+        let msg2 = parse_message("<1>1 2015-01-01T22:50:00Z - - - - -")
+            .expect("Should parse empty message");
+        assert_eq!(msg1.timestamp, msg2.timestamp);
+        // example with fractional minutes
+        let msg1 = parse_message("<1>1 2019-01-20T00:46:39+05:45 - - - - -")
+            .expect("Should parse empty message");
+        let msg2 = parse_message("<1>1 2019-01-19T11:01:39-08:00 - - - - -")
+            .expect("Should parse empty message");
+        assert_eq!(msg1.timestamp, msg2.timestamp);
+    }
+
+    #[test]
+    fn test_complex() {
+        let msg = parse_message("<78>1 2016-01-15T00:04:01+00:00 host1 CROND 10391 - [meta sequenceId=\"29\"] some_message").expect("Should parse complex message");
+        assert_eq!(msg.facility, SyslogFacility::LOG_CRON);
+        assert_eq!(msg.severity, SyslogSeverity::SEV_INFO);
+        assert_eq!(msg.priority(), 78);
+        assert_eq!(msg.hostname.as_deref(), Some("host1"));
+        assert_eq!(msg.appname.as_deref(), Some("CROND"));
+        assert_eq!(msg.procid, Some(message::ProcId::PID(10391)));
+        assert_eq!(msg.msg, String::from("some_message"));
+        assert_eq!(msg.timestamp, Some(1452816241));
+        assert_eq!(msg.sd.len(), 1);
+        let v = msg
+            .sd
+            .find_tuple("meta", "sequenceId")
+            .expect("Should contain meta sequenceId");
+        assert_eq!(v, "29");
+    }
+
+    #[test]
+    fn test_sd_empty() {
+        let msg = parse_message(
+            "<78>1 2016-01-15T00:04:01Z host1 CROND 10391 - [meta@1234] some_message",
+        )
+        .expect("Should parse message with empty structured data");
+        assert_eq!(msg.facility, SyslogFacility::LOG_CRON);
+        assert_eq!(msg.severity, SyslogSeverity::SEV_INFO);
+        assert_eq!(msg.hostname.as_deref(), Some("host1"));
+        assert_eq!(msg.appname.as_deref(), Some("CROND"));
+        assert_eq!(msg.procid, Some(message::ProcId::PID(10391)));
+        assert_eq!(msg.msg, String::from("some_message"));
+        assert_eq!(msg.timestamp, Some(1452816241));
+        assert_eq!(msg.sd.len(), 1);
+        assert_eq!(
+            msg.sd
+                .find_sdid("meta@1234")
+                .expect("should contain meta")
+                .len(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_sd_features() {
+        let msg = parse_message("<78>1 2016-01-15T00:04:01Z host1 CROND 10391 - [meta sequenceId=\"29\" sequenceBlah=\"foo\"][my key=\"value\"][meta bar=\"baz=\"] some_message").expect("Should parse complex message");
+        assert_eq!(msg.facility, SyslogFacility::LOG_CRON);
+        assert_eq!(msg.severity, SyslogSeverity::SEV_INFO);
+        assert_eq!(msg.hostname.as_deref(), Some("host1"));
+        assert_eq!(msg.appname.as_deref(), Some("CROND"));
+        assert_eq!(msg.procid, Some(message::ProcId::PID(10391)));
+        assert_eq!(msg.msg, String::from("some_message"));
+        assert_eq!(msg.timestamp, Some(1452816241));
+        assert_eq!(msg.sd.len(), 2);
+        assert_eq!(
+            msg.sd.find_sdid("meta").expect("should contain meta").len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_sd_with_escaped_quote() {
+        let msg_text = r#"<1>1 - - - - - [meta key="val\"ue"] message"#;
+        let msg = parse_message(msg_text).expect("should parse");
+        assert_eq!(
+            msg.sd
+                .find_tuple("meta", "key")
+                .expect("Should contain meta key"),
+            r#"val"ue"#
+        );
+    }
+
+    #[test]
+    fn test_other_message() {
+        let msg_text = r#"<190>1 2016-02-21T01:19:11+00:00 batch6sj - - - [meta sequenceId="21881798" x-group="37051387"][origin x-service="tracking"] metascutellar conversationalist nephralgic exogenetic graphy streng outtaken acouasm amateurism prenotice Lyonese bedull antigrammatical diosphenol gastriloquial bayoneteer sweetener naggy roughhouser dighter addend sulphacid uneffectless ferroprussiate reveal Mazdaist plaudite Australasian distributival wiseman rumness Seidel topazine shahdom sinsion mesmerically pinguedinous ophthalmotonometer scuppler wound eciliate expectedly carriwitchet dictatorialism bindweb pyelitic idic atule kokoon poultryproof rusticial seedlip nitrosate splenadenoma holobenthic uneternal Phocaean epigenic doubtlessly indirection torticollar robomb adoptedly outspeak wappenschawing talalgia Goop domitic savola unstrafed carded unmagnified mythologically orchester obliteration imperialine undisobeyed galvanoplastical cycloplegia quinquennia foremean umbonal marcgraviaceous happenstance theoretical necropoles wayworn Igbira pseudoangelic raising unfrounced lamasary centaurial Japanolatry microlepidoptera"#;
+        parse_message(msg_text).expect("should parse as text");
+    }
+
+    #[test]
+    fn test_lenient() {
+        let line = "<9.6> 1 2016-1-15 00:04:01.1234567Z h\u{f4}st1\r - - - [flags secure][meta x:\"1\"] hello";
+        parse_message(line).expect_err("strict parsing rejects it");
+        let lenient = parse_message_lenient(line);
+        #[cfg(feature = "strict-only")]
+        lenient.expect_err("there is no leniency in strict-only builds");
+        #[cfg(not(feature = "strict-only"))]
+        {
+            let msg = lenient.expect("should parse");
+            assert_eq!(msg.facility, SyslogFacility::LOG_CRON);
+            assert_eq!(msg.severity, SyslogSeverity::SEV_INFO);
+            assert_eq!(msg.timestamp, Some(1452816241));
+            assert_eq!(msg.timestamp_nanos, Some(123_456_700));
+            assert_eq!(msg.hostname.as_deref(), Some("h\u{f4}st1"));
+            assert_eq!(msg.sd.find_tuple("flags", "secure").unwrap(), "");
+            assert_eq!(msg.sd.find_tuple("meta", "x").unwrap(), "1");
+            assert_eq!(msg.msg, "hello");
+
+            let msg = parse_message_lenient("<13>1 1452816241 host1 - - - no sd").unwrap();
+            assert_eq!(msg.timestamp, Some(1452816241));
+            assert!(msg.sd.is_empty());
+            assert_eq!(msg.msg, "no sd");
+        }
+
+        // valid messages are read the same either way
+        for line in &[
+            r#"<78>1 2016-01-15T00:04:01.5+05:30 host1 CROND 10391 - [meta x="a\nb"] hello"#,
+            "<0>1 - - - - - - msg|trailer",
+            "<1>1 - - - - - [a b=\"c\"]",
+        ] {
+            assert_eq!(
+                parse_message_lenient(line).unwrap(),
+                parse_message(line).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_bom() {
+        let input = "<14>1 2017-07-26T14:47:35.869952+05:30 my_hostname custom_appname 5678 some_unique_msgid - \u{feff}Some other message";
+        let msg = parse_message(input).expect("should parse");
+        assert_eq!(msg.msg, "Some other message");
+        assert!(msg.msg_has_bom);
+        assert_eq!(msg.to_string(), input);
+        let msg_ref = parse_message_ref(input).expect("should parse");
+        assert_eq!(msg_ref.msg, "Some other message");
+        assert!(msg_ref.msg_has_bom);
+
+        let msg = parse_message("<1>1 - - - - - - \u{feff}").expect("should parse");
+        assert_eq!(msg.msg, "");
+        assert!(msg.msg_has_bom);
+        assert_eq!(msg.to_string(), "<1>1 - - - - - - \u{feff}");
+
+        // only one leading BOM is taken, and only at the start
+        let msg = parse_message("<1>1 - - - - - - x\u{feff}").expect("should parse");
+        assert_eq!(msg.msg, "x\u{feff}");
+        assert!(!msg.msg_has_bom);
+        let msg = parse_message("<1>1 - - - - - - \u{feff}\u{feff}x").expect("should parse");
+        assert_eq!(msg.msg, "\u{feff}x");
+
+        // the BOM isn't part of the raw bytes either
+        let msg = parse_message_bytes(b"<1>1 - - - - - - \xef\xbb\xbf\xff").expect("should parse");
+        assert!(msg.msg_has_bom);
+        assert_eq!(msg.msg_raw.as_deref(), Some(&b"\xff"[..]));
+    }
+
+    #[test]
+    fn test_bad_pri() {
+        let msg = parse_message("<4096>1 - - - - - -");
+        assert!(msg.is_err());
+    }
+
+    #[test]
+    fn test_field_too_long() {
+        let input = format!("<1>1 - {} - - - -", "h".repeat(300));
+        let err = parse_message(&input).unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::FieldTooLong("hostname")));
+        assert_eq!(err.offset(), 7 + 255);
+
+        for (input, field) in &[
+            (format!("<1>1 - - {} - - -", "a".repeat(49)), "appname"),
+            (format!("<1>1 - - - {} - -", "1".repeat(129)), "procid"),
+            (format!("<1>1 - - - - {} -", "m".repeat(33)), "msgid"),
+        ] {
+            let err = parse_message(input).unwrap_err();
+            assert!(
+                matches!(err.kind(), ParseErrKind::FieldTooLong(f) if f == field),
+                "{:?}",
+                err
+            );
+        }
+
+        // exactly the maximum is fine
+        let input = format!(
+            "<1>1 - {} {} - {} -",
+            "h".repeat(255),
+            "a".repeat(48),
+            "m".repeat(32)
+        );
+        parse_message(&input).expect("should parse");
+    }
+
+    #[test]
+    fn test_max_pri() {
+        let msg = parse_message("<191>1 - - - - - -").expect("<191> is the largest valid PRI");
+        assert_eq!(msg.facility, SyslogFacility::LOG_LOCAL7);
+        assert_eq!(msg.severity, SyslogSeverity::SEV_DEBUG);
+
+        let err = parse_message("<192>1 - - - - - -").unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::BadFacilityInPri));
+        assert_eq!(err.offset(), 1);
+    }
+
+    #[test]
+    fn test_bad_match() {
+        // we shouldn't be able to parse RFC3164 messages
+        let msg = parse_message("<134>Feb 18 20:53:31 haproxy[376]: I am a message");
+        assert!(msg.is_err());
+    }
+
+    #[test]
+    fn test_example_timestamps() {
+        // these are the example timestamps in the rfc
+
+        let msg = parse_message("<1>1 1985-04-12T23:20:50.52Z host - - - -")
+            .expect("Should parse empty message");
+        assert_eq!(msg.timestamp, Some(482196050));
+        assert_eq!(msg.timestamp_nanos, Some(520000000));
+
+        let msg = parse_message("<1>1 1985-04-12T19:20:50.52+04:00 host - - - -")
+            .expect("Should parse empty message");
+        assert_eq!(msg.timestamp, Some(482167250));
+        assert_eq!(msg.timestamp_nanos, Some(520000000));
+
+        let msg = parse_message("<1>1 1985-04-12T19:20:50+04:00 host - - - -")
+            .expect("Should parse empty message");
+        assert_eq!(msg.timestamp, Some(482167250));
+        assert_eq!(msg.timestamp_nanos, Some(0));
+
+        let msg = parse_message("<1>1 2003-08-24T05:14:15.000003+07:00 host - - - -")
+            .expect("Should parse empty message");
+        assert_eq!(msg.timestamp, Some(1061676855));
+        assert_eq!(msg.timestamp_nanos, Some(3000));
+
+        let msg = parse_message("<1>1 2003-08-24T05:14:15.000000003+07:00 host - - - -");
+        assert!(msg.is_err(), "expected parse fail");
+    }
+
+    #[test]
+    fn test_empty_sd_value() {
+        let msg = parse_message(r#"<29>1 2018-05-14T08:23:01.520Z leyal_test4 mgd 13894 UI_CHILD_EXITED [junos@2636.1.1.1.2.57 pid="14374" return-value="5" core-dump-status="" command="/usr/sbin/mustd"]"#).expect("must parse");
+        assert_eq!(msg.facility, SyslogFacility::LOG_DAEMON);
+        assert_eq!(msg.severity, SyslogSeverity::SEV_NOTICE);
+        assert_eq!(msg.hostname.as_deref(), Some("leyal_test4"));
+        assert_eq!(msg.appname.as_deref(), Some("mgd"));
+        assert_eq!(msg.procid, Some(message::ProcId::PID(13894)));
+        assert_eq!(msg.msg, String::from(""));
+        assert_eq!(msg.timestamp, Some(1526286181));
+        assert_eq!(msg.timestamp_nanos, Some(520000000));
+        assert_eq!(msg.sd.len(), 1);
+        let sd_id = msg.sd.keys().next().unwrap();
+        assert_eq!(message::enterprise_number(sd_id), Some("2636.1.1.1.2.57"));
+        let sd = msg
+            .sd
+            .find_sdid("junos@2636.1.1.1.2.57")
+            .expect("should contain root SD");
+        let expected = {
+            let mut expected = BTreeMap::new();
+            expected.insert("pid", "14374");
+            expected.insert("return-value", "5");
+            expected.insert("core-dump-status", "");
+            expected.insert("command", "/usr/sbin/mustd");
+            expected
+                .into_iter()
+                .map(|(k, v)| (message::SDParamIDType::from(k), v.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        assert_eq!(sd, &expected);
+    }
+
+    #[test]
+    fn test_fields_start_with_dash() {
+        let msg = parse_message("<39>1 2018-05-15T20:56:58+00:00 -web1west -201805020050-bc5d6a47c3-master - - [meta sequenceId=\"28485532\"] 25450-uWSGI worker 6: getaddrinfo*.gaih_getanswer: got type \"DNAME\"").expect("should parse");
+        assert_eq!(msg.hostname.as_deref(), Some("-web1west"));
+        assert_eq!(
+            msg.appname.as_deref(),
+            Some("-201805020050-bc5d6a47c3-master")
+        );
+        assert_eq!(
+            msg.sd.find_tuple("meta", "sequenceId"),
+            Some(&"28485532".to_string())
+        );
+        assert_eq!(
+            msg.msg,
+            "25450-uWSGI worker 6: getaddrinfo*.gaih_getanswer: got type \"DNAME\"".to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_message_with_multi() {
+        let opts = ParseOptions::default();
+        let msg = parse_message_with::<MultiStructuredData>(
+            r#"<78>1 - host1 app - - [foo bar="baz" bar="bing"][empty@1][foo bar="bop" a="1"] hi"#,
+            &opts,
+        )
+        .unwrap();
+        assert_eq!(msg.msg, "hi");
+        assert_eq!(msg.sd.len(), 2);
+        assert_eq!(msg.sd.find_all_tuples("foo", "bar"), ["baz", "bing", "bop"]);
+        assert_eq!(msg.sd.find_all_tuples("foo", "a"), ["1"]);
+        assert!(msg.sd.find_all_tuples("foo", "missing").is_empty());
+        assert!(msg.sd.find_all_tuples("missing", "bar").is_empty());
+        assert!(msg.sd.find_sdid("empty@1").unwrap().is_empty());
+
+        let msg = parse_message_with::<MultiStructuredData>("<78>1 - - - - - - hi", &opts).unwrap();
+        assert!(msg.sd.is_empty());
+
+        // the default container behaves exactly like parse_message
+        let input = r#"<78>1 - - - - - [foo bar="baz" bar="bing"] hi"#;
+        let msg = parse_message_with::<StructuredData>(input, &opts).unwrap();
+        assert_eq!(msg, parse_message(input).unwrap());
+        assert_eq!(msg.sd.find_tuple("foo", "bar").unwrap(), "bing");
+    }
+
+    type CanonicalSd = BTreeMap<String, BTreeMap<String, String>>;
+
+    /// Inputs for `sd_backend_tests!`, with the structured data each should come out as when only
+    /// the last value of a repeated param is kept
+    fn sd_backend_cases() -> Vec<(&'static str, CanonicalSd)> {
+        let sd = |elements: &[(&str, &[(&str, &str)])]| -> CanonicalSd {
+            elements
+                .iter()
+                .map(|(id, params)| {
+                    let params = params
+                        .iter()
+                        .map(|(k, v)| (String::from(*k), String::from(*v)))
+                        .collect();
+                    (String::from(*id), params)
+                })
+                .collect()
+        };
+        vec![
+            ("<1>1 - - - - - - hi", sd(&[])),
+            (
+                r#"<78>1 2016-01-15T00:04:01Z host1 CROND 10391 - [meta sequenceId="29" sequenceBlah="foo"][my key="value"] some_message"#,
+                sd(&[
+                    ("meta", &[("sequenceId", "29"), ("sequenceBlah", "foo")]),
+                    ("my", &[("key", "value")]),
+                ]),
+            ),
+            (
+                r#"<1>1 - - - - - [foo bar="baz" bar="bing"][empty@32473][foo bar="bop" a=""] -"#,
+                sd(&[("empty@32473", &[]), ("foo", &[("a", ""), ("bar", "bop")])]),
+            ),
+            (
+                r#"<1>1 - - - - - [x q="a \"b\" \\ \] \c" u="héllo"] -"#,
+                sd(&[("x", &[("q", r#"a "b" \ ] \c"#), ("u", "héllo")])]),
+            ),
+        ]
+    }
+
+    /// Run the same structured-data assertions against each container the parser can fill in.
+    /// A backend is given as functions to parse a message into it, list its SD-IDs, and look up
+    /// an element or a param the way `StructuredData::find_sdid` and
+    /// `StructuredData::find_tuple` do.
+    macro_rules! sd_backend_tests {
+        ($($name:ident {
+            parse: $parse:expr,
+            sd_ids: $sd_ids:expr,
+            find_sdid: $find_sdid:expr,
+            find_tuple: $find_tuple:expr $(,)?
+        })*) => {$(
+            #[test]
+            fn $name() {
+                for (input, expected) in sd_backend_cases() {
+                    let sd = ($parse)(input);
+                    let ids: Vec<String> = ($sd_ids)(&sd);
+                    let as_btreemap = ids
+                        .iter()
+                        .map(|id| (id.clone(), ($find_sdid)(&sd, id.as_str()).unwrap()))
+                        .collect::<CanonicalSd>();
+                    assert_eq!(as_btreemap, expected, "{}", input);
+                    for (id, params) in &expected {
+                        for (param, value) in params {
+                            assert_eq!(
+                                ($find_tuple)(&sd, id.as_str(), param.as_str()).as_ref(),
+                                Some(value),
+                                "{}",
+                                input
+                            );
+                        }
+                        assert_eq!(($find_tuple)(&sd, id.as_str(), "missing"), None);
+                    }
+                    assert_eq!(($find_sdid)(&sd, "missing"), None);
+                    assert_eq!(($find_tuple)(&sd, "missing", "bar"), None);
+                }
+            }
+        )*};
+    }
+
+    sd_backend_tests! {
+        test_sd_backend_structured_data {
+            parse: |s| parse_message(s).unwrap().sd,
+            sd_ids: |sd: &StructuredData| sd.keys().map(|id| id.to_string()).collect(),
+            find_sdid: |sd: &StructuredData, id: &str| {
+                sd.find_sdid(id).map(|element| {
+                    element
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.clone()))
+                        .collect::<BTreeMap<_, _>>()
+                })
+            },
+            find_tuple: |sd: &StructuredData, id: &str, param: &str| {
+                sd.find_tuple(id, param).cloned()
+            },
+        }
+        test_sd_backend_multi_structured_data {
+            parse: |s| {
+                parse_message_with::<MultiStructuredData>(s, &ParseOptions::default())
+                    .unwrap()
+                    .sd
+            },
+            sd_ids: |sd: &MultiStructuredData| sd.iter().map(|(id, _)| id.to_string()).collect(),
+            find_sdid: |sd: &MultiStructuredData, id: &str| {
+                sd.find_sdid(id).map(|element| {
+                    element
+                        .iter()
+                        .map(|(k, values)| (k.to_string(), values.last().unwrap().clone()))
+                        .collect::<BTreeMap<_, _>>()
+                })
+            },
+            find_tuple: |sd: &MultiStructuredData, id: &str, param: &str| {
+                sd.find_all_tuples(id, param).last().cloned()
+            },
+        }
+        test_sd_backend_structured_data_ref {
+            parse: |s| parse_message_ref(s).unwrap().sd,
+            sd_ids: |sd: &StructuredDataRef| sd.iter().map(|(id, _)| String::from(id)).collect(),
+            find_sdid: |sd: &StructuredDataRef, id: &str| {
+                sd.find_sdid(id).map(|element| {
+                    element
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect::<BTreeMap<_, _>>()
+                })
+            },
+            find_tuple: |sd: &StructuredDataRef, id: &str, param: &str| {
+                sd.find_tuple(id, param).map(String::from)
+            },
+        }
+    }
+
+    #[test]
+    fn test_nil_msgid_before_sd() {
+        for (input, body) in &[
+            (r#"<13>1 - host app 1234 - [meta sequenceId="1"] hi"#, "hi"),
+            (r#"<13>1 - host app 1234 - [meta sequenceId="1"]"#, ""),
+            (
+                r#"<13>1 - host app 1234 - [meta sequenceId="1"][x@1 a="b"] hi"#,
+                "hi",
+            ),
+        ] {
+            let msg = parse_message(input).unwrap_or_else(|e| panic!("{}: {}", input, e));
+            assert_eq!(msg.procid, Some(message::ProcId::PID(1234)));
+            assert_eq!(msg.msgid, None);
+            assert_eq!(msg.sd.find_tuple("meta", "sequenceId").unwrap(), "1");
+            assert_eq!(msg.msg, *body);
+        }
+    }
+
+    #[test]
+    fn test_nil_field_combinations() {
+        let values = ["host1", "app", "1234", "ID47"];
+        for mask in 0..16 {
+            let fields: Vec<&str> = values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| if mask & (1 << i) != 0 { *v } else { "-" })
+                .collect();
+            let header = fields.join(" ");
+            let msg = parse_message(format!("<13>1 - {} - hello", header))
+                .unwrap_or_else(|e| panic!("{:?} should parse: {}", header, e));
+
+            assert_eq!(
+                msg.hostname.as_deref(),
+                Some(fields[0]).filter(|f| *f != "-")
+            );
+            assert_eq!(
+                msg.appname.as_deref(),
+                Some(fields[1]).filter(|f| *f != "-")
+            );
+            assert_eq!(msg.procid.is_some(), fields[2] != "-");
+            assert_eq!(msg.msgid.as_deref(), Some(fields[3]).filter(|f| *f != "-"));
+            assert_eq!(msg.msg, "hello");
+
+            // the parsed fields reproduce the exact nil/value pattern
+            let procid = msg.procid.as_ref().map(|p| p.to_string());
+            let reserialized = [
+                msg.hostname.as_deref(),
+                msg.appname.as_deref(),
+                procid.as_deref(),
+                msg.msgid.as_deref(),
+            ]
+            .iter()
+            .map(|f| f.unwrap_or("-"))
+            .collect::<Vec<_>>()
+            .join(" ");
+            assert_eq!(reserialized, header);
+        }
+    }
+
+    #[cfg(not(feature = "strict-only"))]
+    #[test]
+    fn test_parse_into() {
+        let opts = ParseOptions {
+            trailing_field_delimiter: Some('#'),
+            ..Default::default()
+        };
+        let mut msg = parse_message_with_options(
+            "<78>1 2016-01-15T00:04:01Z host1 CROND worker ID47 [meta x=\"1\"] a long first message#t1",
+            &opts,
+        )
+        .unwrap();
+        let msg_buf = msg.msg.as_ptr();
+
+        let input = "<13>1 - - - named ID48 - second#t2";
+        parse_into(input, &mut msg, &opts).unwrap();
+        assert_eq!(msg, parse_message_with_options(input, &opts).unwrap());
+        assert_eq!(msg.msg.as_ptr(), msg_buf);
+        assert_eq!(msg.procid, Some(ProcId::Name(String::from("named"))));
+
+        let input = "<13>1 - - - 1234 - - third";
+        parse_into(input, &mut msg, &opts).unwrap();
+        assert_eq!(msg, parse_message_with_options(input, &opts).unwrap());
+
+        parse_into("<13>1 - bad", &mut msg, &opts).expect_err("should fail");
+        assert_eq!(msg.msg, "");
+        assert_eq!(msg.procid, None);
+        assert!(msg.sd.is_empty());
+    }
+
+    #[test]
+    fn test_malformed_timestamp_fields() {
+        for input in &[
+            "<1>1 2017-7-26T14:47:35Z - - - - -",
+            "<1>1 2017-07-26T14:4:35Z - - - - -",
+            "<1>1 17-07-26T14:47:35Z - - - - -",
+            "<1>1 2017-07-26T14:47:35+05:3x - - - - -",
+            "<1>1 2017-07-26T14:47:35+05-30 - - - - -",
+            "<1>1 2017-07-26T14:47:35+0",
+        ] {
+            parse_message(input).expect_err(input);
+        }
+    }
+
+    #[cfg(not(feature = "strict-only"))]
+    #[test]
+    fn test_unpadded_timestamp_fields() {
+        let opts = ParseOptions {
+            allow_unpadded_timestamp_fields: true,
+            ..Default::default()
+        };
+        let padded = parse_message("<1>1 2016-01-05T00:04:01Z - - - - -").unwrap();
+        for input in &[
+            "<1>1 2016-1-5T0:4:1Z - - - - -",
+            "<1>1 2016-01-5T00:4:01Z - - - - -",
+            "<1>1 2016-01-05T00:04:01Z - - - - -",
+        ] {
+            let msg = parse_message_with_options(input, &opts).expect(input);
+            assert_eq!(msg.timestamp, padded.timestamp, "{}", input);
+            assert_eq!(msg.timestamp, Some(1451952241));
+        }
+        let msg = parse_message_with_options("<1>1 2016-1-5T0:4:1.25+5:30 - - - - -", &opts);
+        assert!(msg.is_err(), "the offset still needs two-digit fields");
+
+        for input in &[
+            "<1>1 2016-1-5T0:4:1Z - - - - -",
+            "<1>1 2016--5T00:04:01Z - - - - -",
+            "<1>1 2016-001-05T00:04:01Z - - - - -",
+        ] {
+            parse_message(input).expect_err(input);
+        }
+        parse_message_with_options("<1>1 2016--5T00:04:01Z - - - - -", &opts).expect_err("empty");
+        parse_message_with_options("<1>1 2016-001-05T00:04:01Z - - - - -", &opts)
+            .expect_err("three digits");
+    }
+
+    #[test]
+    fn test_sd_msg_separator() {
+        // exactly one space separates the SD from the MSG; any further spaces are message content
+        for (input, expected) in &[
+            ("<1>1 - - - - - -", ""),
+            ("<1>1 - - - - - - ", ""),
+            ("<1>1 - - - - - - msg", "msg"),
+            ("<1>1 - - - - - -  msg", " msg"),
+            ("<1>1 - - - - - -   ", "  "),
+            ("<1>1 - - - - - [a b=\"c\"] msg", "msg"),
+            ("<1>1 - - - - - [a b=\"c\"]  msg", " msg"),
+            ("<1>1 - - - - - [a][b c=\"d\"] msg", "msg"),
+            ("<1>1 - - - - - [a]", ""),
+        ] {
+            let msg = parse_message(input).expect("should parse");
+            assert_eq!(&msg.msg, expected, "{:?}", input);
+        }
+
+        #[cfg(not(feature = "strict-only"))]
+        {
+            let opts = ParseOptions {
+                allow_missing_sd: true,
+                ..Default::default()
+            };
+            let msg = parse_message_with_options("<1>1 - - - - - msg", &opts).unwrap();
+            assert!(msg.sd.is_empty());
+            assert_eq!(msg.msg, "msg");
+        }
+    }
+
+    #[test]
+    fn test_error_offsets() {
+        for (input, kind, offset) in &[
+            ("x", "ExpectedTokenErr", 0),
+            ("<999>1 - - - - - -", "BadFacilityInPri", 1),
+            ("<1>1 2015-13-01T00:00:00Z - - - - -", "InvalidMonth", 5),
+            ("<1>1 2015-01-01T00:00:00Z\t- - - - -", "BadSeparator", 25),
+            ("<1>1 2015-01-01T0x:00:00Z - - - - -", "ExpectedClass", 17),
+            (
+                "<1>1 - host app - - [meta x=\"1\" y]",
+                "ExpectedTokenErr",
+                33,
+            ),
+            (
+                "<1>1 - host app - - [meta x=\"unterminated",
+                "UnexpectedEndOfInput",
+                41,
+            ),
+            ("<1>1 - host", "UnexpectedEndOfInput", 11),
+        ] {
+            let err = parse_message(input).expect_err(input);
+            assert!(format!("{:?}", err.kind()).starts_with(kind), "{:?}", err);
+            assert_eq!(err.offset(), *offset, "{:?} for {:?}", err, input);
+        }
+
+        let err =
+            parse_message_bytes_with_options(b"<1>1 - h\xff - - - -", &ParseOptions::default())
+                .unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::BaseUnicodeError(_)));
+        assert_eq!(err.offset(), 8);
+        assert_eq!(
+            err.to_string(),
+            "unicode error: invalid utf-8 sequence of 1 bytes from index 8 at byte 8"
+        );
+
+        // the offset of an error in the structured data points into the SD, not at its start
+        let err = parse_structured_data_only("<1>1 - - - - - [a b=c]").unwrap_err();
+        assert_eq!(err.offset(), 20);
+    }
+
+    #[test]
+    fn test_strict_sd_names() {
+        let input = r#"<1>1 - - - - - [me"ta x="1"] -"#;
+        #[cfg(not(feature = "strict-only"))]
+        {
+            let msg = parse_message(input).expect("lenient parsing keeps the stray quote");
+            assert!(msg.sd.find_sdid("me\"ta").is_some());
+        }
+        let err = parse_message_strict(input).unwrap_err();
+        match err.kind() {
+            ParseErrKind::InvalidSdName(name) => assert_eq!(name, "me\"ta"),
+            other => panic!("unexpected error {:?}", other),
+        }
+        assert_eq!(err.offset(), 16);
+
+        for (input, offset) in &[
+            (r#"<1>1 - - - - - [meta x"y="1"] -"#, 21),
+            (r#"<1>1 - - - - - [meta x@y="1"] -"#, 21),
+            (r#"<1>1 - - - - - [a@b x="1"] -"#, 16),
+            (r#"<1>1 - - - - - [a@1@2 x="1"] -"#, 16),
+            (r#"<1>1 - - - - - [@32473 x="1"] -"#, 16),
+            (
+                r#"<1>1 - - - - - [abcdefghijklmnopqrstuvwxyz0123456 x="1"] -"#,
+                16,
+            ),
+            ("<1>1 - - - - - [m\u{e9}ta x=\"1\"] -", 16),
+        ] {
+            #[cfg(not(feature = "strict-only"))]
+            assert!(parse_message(input).is_ok(), "{:?}", input);
+            let err = parse_message_strict(input).expect_err(input);
+            assert!(
+                matches!(err.kind(), ParseErrKind::InvalidSdName(_)),
+                "{:?}",
+                err
+            );
+            assert_eq!(err.offset(), *offset, "{:?}", input);
+        }
+
+        let msg = parse_message_strict(
+            r#"<1>1 - - - - - [exampleSDID@32473 iut="3" eventSource="App"][meta sequenceId="1"] -"#,
+        )
+        .unwrap();
+        assert_eq!(msg.sd.len(), 2);
+    }
+
+    #[cfg(not(feature = "strict-only"))]
+    #[test]
+    fn test_extra_header_fields() {
+        let opts = ParseOptions {
+            collect_extra_header_fields: true,
+            ..Default::default()
+        };
+        let msg = parse_message_with_options(
+            r#"<78>1 - host1 app 42 msgid tenant=acme eu-west [meta sequenceId="1"] hi there"#,
+            &opts,
+        )
+        .unwrap();
+        assert_eq!(msg.msgid.as_deref(), Some("msgid"));
+        assert_eq!(msg.sd.find_tuple("_extra", "1").unwrap(), "tenant=acme");
+        assert_eq!(msg.sd.find_tuple("_extra", "2").unwrap(), "eu-west");
+        assert_eq!(msg.sd.find_tuple("meta", "sequenceId").unwrap(), "1");
+        assert_eq!(msg.msg, "hi there");
+
+        let msg =
+            parse_message_with_options("<78>1 - host1 app 42 msgid extra - hi", &opts).unwrap();
+        assert_eq!(msg.sd.len(), 1);
+        assert_eq!(msg.sd.find_tuple("_extra", "1").unwrap(), "extra");
+        assert_eq!(msg.msg, "hi");
+
+        // without extra fields, nothing changes
+        let input = r#"<78>1 - host1 app 42 msgid [meta sequenceId="1"] hi"#;
+        assert_eq!(
+            parse_message_with_options(input, &opts).unwrap(),
+            parse_message(input).unwrap()
+        );
+        // with no structured data to stop at, they aren't extra fields after all
+        assert!(parse_message_with_options("<78>1 - host1 app 42 msgid extra", &opts).is_err());
+
+        let err = parse_message("<78>1 - host1 app 42 msgid extra - hi").unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::ExpectedTokenErr('[')));
+        assert_eq!(err.offset(), 27);
+    }
+
+    #[cfg(not(feature = "strict-only"))]
+    #[test]
+    fn test_lowercase_t_and_z() {
+        let input = "<1>1 1985-04-12t23:20:50.52z host - - - -";
+        parse_message(input).expect_err("RFC 5424 requires uppercase");
+        let opts = ParseOptions {
+            allow_lowercase_t_and_z: true,
+            ..Default::default()
+        };
+        let msg = parse_message_with_options(input, &opts).expect("should parse");
+        assert_eq!(
+            msg,
+            parse_message("<1>1 1985-04-12T23:20:50.52Z host - - - -").unwrap()
+        );
+        assert_eq!(msg.timestamp, Some(482196050));
+        assert_eq!(msg.timestamp_nanos, Some(520000000));
+
+        let msg = parse_message_with_options("<1>1 1985-04-12t23:20:50-04:00 - - - - -", &opts)
+            .expect("should parse");
+        assert_eq!(msg.timestamp, Some(482196050 + 4 * 3600));
+        let msg = parse_message_lenient(input).expect("should parse");
+        assert_eq!(msg.timestamp, Some(482196050));
+    }
+
+    #[cfg(not(feature = "strict-only"))]
+    #[test]
+    fn test_space_time_separator() {
+        let opts = ParseOptions {
+            allow_space_time_separator: true,
+            ..Default::default()
+        };
+        let msg =
+            parse_message_with_options("<78>1 2016-01-15 00:04:01Z host1 CROND - - - hi", &opts)
+                .unwrap();
+        assert_eq!(msg.timestamp, Some(1452816241));
+        assert_eq!(msg.hostname.as_deref(), Some("host1"));
+        assert_eq!(msg.appname.as_deref(), Some("CROND"));
+        let msg =
+            parse_message_with_options("<78>1 2016-01-15 00:04:01.5+01:00 host1 - - - - hi", &opts)
+                .unwrap();
+        assert_eq!(msg.timestamp, Some(1452816241 - 3600));
+        assert_eq!(msg.timestamp_nanos, Some(500_000_000));
+        assert_eq!(msg.hostname.as_deref(), Some("host1"));
+
+        // `T` is still accepted, and a date with no time after the space still isn't
+        assert!(
+            parse_message_with_options("<78>1 2016-01-15T00:04:01Z host1 - - - - hi", &opts)
+                .is_ok()
+        );
+        assert!(parse_message_with_options("<78>1 2016-01-15 host1 - - - - hi", &opts).is_err());
+
+        let err = parse_message("<78>1 2016-01-15 00:04:01Z host1 - - - - hi").unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::ExpectedTokenErr('T')));
+        assert_eq!(err.offset(), 16);
+    }
+
+    #[cfg(not(feature = "strict-only"))]
+    #[test]
+    fn test_dotted_pri() {
+        let opts = ParseOptions {
+            allow_dotted_pri: true,
+            ..Default::default()
+        };
+        let dotted = parse_message_with_options("<9.6>1 - host - - - - hi", &opts).unwrap();
+        assert_eq!(dotted.facility, SyslogFacility::LOG_CRON);
+        assert_eq!(dotted.severity, SyslogSeverity::SEV_INFO);
+        assert_eq!(
+            dotted,
+            parse_message_with_options("<78>1 - host - - - - hi", &opts).unwrap()
+        );
+
+        // the combined form still works
+        let msg = parse_message_with_options("<165>1 - - - - - -", &opts).unwrap();
+        assert_eq!(msg.facility, SyslogFacility::LOG_LOCAL4);
+
+        let err = parse_message_with_options("<24.1>1 - - - - - -", &opts).unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::BadFacilityInPri));
+        assert_eq!(err.offset(), 1);
+        let err = parse_message_with_options("<9.8>1 - - - - - -", &opts).unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::BadSeverityInPri));
+        assert_eq!(err.offset(), 3);
+        let err = parse_message_with_options("<9.>1 - - - - - -", &opts).unwrap_err();
+        assert_eq!(err.offset(), 3);
+
+        let err = parse_message("<9.6>1 - host - - - - hi").unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::ExpectedTokenErr('>')));
+        assert_eq!(err.offset(), 2);
+    }
+
+    #[test]
+    fn test_calendar_dates() {
+        for date in &["2016-02-29", "2000-02-29", "2015-12-31", "2016-04-30"] {
+            let input = format!("<1>1 {}T00:00:00Z - - - - -", date);
+            assert!(parse_message(&input).is_ok(), "{}", date);
+        }
+        for date in &[
+            "2015-02-29",
+            "1900-02-29",
+            "2016-02-30",
+            "2016-04-31",
+            "2016-01-00",
+            "2016-01-32",
+        ] {
+            let input = format!("<1>1 {}T00:00:00Z - - - - -", date);
+            let err = parse_message(&input).expect_err(date);
+            assert!(
+                matches!(err.kind(), ParseErrKind::InvalidDate(_)),
+                "{}: {:?}",
+                date,
+                err
+            );
+            assert_eq!(err.offset(), 5);
+        }
+        let err = parse_message("<1>1 2016-13-40T00:00:00Z - - - - -").unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::InvalidMonth(13)));
+        let err = parse_message("<1>1 2016-00-01T00:00:00Z - - - - -").unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::InvalidMonth(0)));
+    }
+
+    #[test]
+    fn test_expected_class() {
+        for (input, expected, found, offset) in &[
+            (
+                "<1>1 2015-01-01T0x:00:00Z - - - - -",
+                CharClass::Digit,
+                Some('x'),
+                17,
+            ),
+            (
+                "<1>1 2015-1-01T00:00:00Z - - - - -",
+                CharClass::Digit,
+                Some('-'),
+                11,
+            ),
+            (
+                "<1>1 2015-01-01T00:00:00.Z - - - - -",
+                CharClass::Digit,
+                Some('Z'),
+                25,
+            ),
+            ("<1>1 2015-01-01T00:00:0", CharClass::Digit, None, 23),
+            ("<x>1 - - - - - -", CharClass::Digit, Some('x'), 1),
+            (
+                "<1>1 - \u{7}host - - - -",
+                CharClass::Printable,
+                Some('\u{7}'),
+                7,
+            ),
+        ] {
+            let err = parse_message(input).expect_err(input);
+            match err.kind() {
+                ParseErrKind::ExpectedClass {
+                    expected: e,
+                    found: f,
+                } => {
+                    assert_eq!(e, expected, "{:?}", input);
+                    assert_eq!(f, found, "{:?}", input);
+                }
+                other => panic!("{:?}: unexpected error {:?}", input, other),
+            }
+            assert_eq!(err.offset(), *offset, "{:?}", input);
+        }
+
+        let err = parse_message("<1>1 2015-01-01T0x:00:00Z - - - - -").unwrap_err();
+        assert_eq!(err.to_string(), "expected a digit but found 'x' at byte 17");
+        let err = parse_message("<1>1 2015-01-01T00:00:0").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected a digit but found end of input at byte 23"
+        );
+    }
+
+    #[test]
+    fn test_truncated() {
+        let err =
+            parse_message("<39>1 2018-05-15T20:56:58+00:00 -web1west -").expect_err("should fail");
+        assert_eq!(
+            mem::discriminant(err.kind()),
+            mem::discriminant(&ParseErrKind::UnexpectedEndOfInput)
+        );
+    }
+
+    #[test]
+    fn test_missing_timestamp() {
+        for input in &["<13>1", "<13>1 "] {
+            let err = parse_message(input).expect_err("should fail");
+            assert!(
+                matches!(err.kind(), ParseErrKind::MissingField("timestamp")),
+                "{:?}: {:?}",
+                input,
+                err
+            );
+        }
+        let err = parse_message("<13>1\t- - - - - -").expect_err("should fail");
+        assert!(matches!(err.kind(), ParseErrKind::BadSeparator('\t')));
+    }
+
+    #[cfg(not(feature = "strict-only"))]
+    #[test]
+    fn test_missing_sd() {
+        let opts = ParseOptions {
+            allow_missing_sd: true,
+            ..Default::default()
+        };
+
+        let msg = parse_message_with_options("<1>1 - - - - - - just a message", &opts)
+            .expect("should parse");
+        assert!(msg.sd.is_empty());
+        assert_eq!(msg.msg, "just a message");
+
+        parse_message("<1>1 - - - - - just a message").expect_err("SD is required by default");
+        let msg = parse_message_with_options("<1>1 - - - - - just a message", &opts)
+            .expect("should parse");
+        assert!(msg.sd.is_empty());
+        assert_eq!(msg.msg, "just a message");
+
+        // with a field fewer, the first word of the message is taken as the MSGID
+        let msg =
+            parse_message_with_options("<1>1 - - - - just a message", &opts).expect("should parse");
+        assert_eq!(msg.msgid, Some("just".to_string()));
+        assert_eq!(msg.msg, "a message");
+
+        let msg = parse_message_with_options("<1>1 - - - - - [meta x=\"1\"] hi", &opts)
+            .expect("should parse");
+        assert_eq!(msg.sd.find_tuple("meta", "x"), Some(&"1".to_string()));
+        assert_eq!(msg.msg, "hi");
+    }
+
+    #[cfg(feature = "interning")]
+    #[test]
+    fn test_parser_interning() {
+        use super::Parser;
+        use std::sync::Arc;
+
+        let mut parser = Parser::new(ParseOptions::default(), 2);
+        let a = parser
+            .parse("<1>1 - host1 app - - - one")
+            .expect("should parse");
+        let b = parser
+            .parse("<1>1 - host1 app - - - two")
+            .expect("should parse");
+        assert!(Arc::ptr_eq(
+            a.hostname.as_ref().unwrap(),
+            b.hostname.as_ref().unwrap()
+        ));
+        assert!(Arc::ptr_eq(
+            a.appname.as_ref().unwrap(),
+            b.appname.as_ref().unwrap()
+        ));
+        assert_eq!(parser.interned_len(), 2);
+
+        parser
+            .parse("<1>1 - host2 app - - - three")
+            .expect("should parse");
+        assert_eq!(parser.interned_len(), 2);
+        assert_eq!(a.hostname.as_deref(), Some("host1"));
+    }
+
+    #[cfg(feature = "sd-interning")]
+    #[test]
+    fn test_parser_sd_interning() {
+        use super::Parser;
+        use std::sync::Arc;
+
+        let mut parser = Parser::new(ParseOptions::default(), 16);
+        let input = r#"<1>1 - host1 app - - [meta sequenceId="1"][origin ip="10.0.0.1"] hi"#;
+        let a = parser.parse(input).expect("should parse");
+        let b = parser.parse(input).expect("should parse");
+        let (a_id, a_params) = a.sd.get_key_value("meta").unwrap();
+        let (b_id, b_params) = b.sd.get_key_value("meta").unwrap();
+        assert!(Arc::ptr_eq(a_id, b_id));
+        assert!(Arc::ptr_eq(
+            a_params.get_key_value("sequenceId").unwrap().0,
+            b_params.get_key_value("sequenceId").unwrap().0
+        ));
+        // meta, sequenceId, origin, and ip, kept apart from the host and app names
+        assert_eq!(parser.sd_interned_len(), 4);
+        assert_eq!(parser.interned_len(), 2);
+        assert_eq!(a, parse_message(input).unwrap());
+
+        // parsing without a Parser still works, just without sharing
+        let c = parse_message(input).unwrap();
+        assert!(!Arc::ptr_eq(c.sd.get_key_value("meta").unwrap().0, a_id));
+    }
+
+    #[cfg(not(feature = "strict-only"))]
+    #[test]
+    fn test_utf8_hostname_near_limit() {
+        let hostname = "é".repeat(255);
+        let msg_text = format!("<1>1 - {} app - - - message", hostname);
+        parse_message(&msg_text).expect_err("should reject non-ASCII hostname by default");
+
+        let opts = ParseOptions {
+            allow_utf8_in_header: true,
+            ..Default::default()
+        };
+        let msg = parse_message_with_options(&msg_text, &opts).expect("should parse");
+        assert_eq!(msg.hostname.as_deref(), Some(hostname.as_str()));
+        assert_eq!(msg.appname.as_deref(), Some("app"));
+        assert_eq!(msg.msg, String::from("message"));
+    }
+
+    #[cfg(not(feature = "strict-only"))]
+    #[test]
+    fn test_cr_before_separator() {
+        let msg_text = "<78>1 2016-01-15T00:04:01Z\r host1\r CROND 10391 -\r [meta sequenceId=\"29\"]\r some_message";
+        let err = parse_message(msg_text).expect_err("should reject CR by default");
+        assert_eq!(
+            mem::discriminant(err.kind()),
+            mem::discriminant(&ParseErrKind::BadSeparator('\r'))
+        );
+
+        let opts = ParseOptions {
+            allow_cr_before_separator: true,
+            ..Default::default()
+        };
+        let msg = parse_message_with_options(msg_text, &opts).expect("should parse");
+        assert_eq!(msg.timestamp, Some(1452816241));
+        assert_eq!(msg.hostname.as_deref(), Some("host1"));
+        assert_eq!(msg.appname.as_deref(), Some("CROND"));
+        assert_eq!(msg.procid, Some(message::ProcId::PID(10391)));
+        assert_eq!(msg.msgid, None);
+        assert_eq!(msg.sd.find_tuple("meta", "sequenceId").unwrap(), "29");
+        assert_eq!(msg.msg, String::from("some_message"));
+
+        let msg = parse_message_with_options("<1>1 - - - - - -\r some_message", &opts)
+            .expect("should parse");
+        assert_eq!(msg.msg, String::from("some_message"));
+    }
+
+    #[test]
+    fn test_duplicate_sd_param_warnings() {
+        let msg_text =
+            r#"<1>1 - - - - - [meta a="1" b="2" a="3"][meta b="4"][other a="5"] message"#;
+        let (msg, warnings) =
+            parse_message_with_warnings(msg_text, &ParseOptions::default()).expect("should parse");
+        assert_eq!(msg.sd.find_tuple("meta", "a").unwrap(), "3");
+        assert_eq!(msg.sd.find_tuple("meta", "b").unwrap(), "4");
+        assert_eq!(
+            warnings,
+            vec![
+                ParseWarning::DuplicateSdParam {
+                    sd_id: String::from("meta"),
+                    param: String::from("a")
+                },
+                ParseWarning::DuplicateSdParam {
+                    sd_id: String::from("meta"),
+                    param: String::from("b")
+                },
+            ]
+        );
+
+        let (_, warnings) = parse_message_with_warnings(
+            r#"<1>1 - - - - - [meta a="1"][other a="2"] message"#,
+            &ParseOptions::default(),
+        )
+        .expect("should parse");
+        assert!(warnings.is_empty());
+    }
+
+    #[cfg(not(feature = "strict-only"))]
+    #[test]
+    fn test_bare_sd_params() {
+        let input = r#"<1>1 - - - - - [flags secure verbose][meta x="1" quiet] msg"#;
+        parse_message(input).expect_err("bare params are rejected by default");
+
+        let opts = ParseOptions {
+            allow_bare_sd_params: true,
+            ..Default::default()
+        };
+        let msg = parse_message_with_options(input, &opts).expect("should parse");
+        assert_eq!(msg.sd.find_tuple("flags", "secure").unwrap(), "");
+        assert_eq!(msg.sd.find_tuple("flags", "verbose").unwrap(), "");
+        assert_eq!(msg.sd.find_tuple("meta", "x").unwrap(), "1");
+        assert_eq!(msg.sd.find_tuple("meta", "quiet").unwrap(), "");
+        assert_eq!(msg.msg, "msg");
+
+        parse_message_with_options(r#"<1>1 - - - - - [flags secure=] msg"#, &opts)
+            .expect_err("an `=` still needs a value");
+
+        let input = r#"<1>1 - - - - - [x a"b] msg"#;
+        parse_message_with_options(input, &opts).expect("names aren't checked by default");
+        let strict = ParseOptions {
+            strict_sd_names: true,
+            ..opts
+        };
+        let err = parse_message_with_options(input, &strict).unwrap_err();
+        match err.kind() {
+            ParseErrKind::InvalidSdName(name) => assert_eq!(name, "a\"b"),
+            other => panic!("unexpected error {:?}", other),
+        }
+        assert_eq!(err.offset(), 18);
+        parse_message_with_options(r#"<1>1 - - - - - [x ok] msg"#, &strict)
+            .expect("valid bare names still parse");
+    }
+
+    #[cfg(not(feature = "strict-only"))]
+    #[test]
+    fn test_colon_sd_separator() {
+        let opts = ParseOptions {
+            allow_colon_sd_separator: true,
+            ..Default::default()
+        };
+        let expected = parse_message(r#"<1>1 - - - - - [meta sequenceId="29" a:b="c"] -"#).unwrap();
+        for input in &[
+            r#"<1>1 - - - - - [meta sequenceId:"29" a:b:"c"] -"#,
+            r#"<1>1 - - - - - [meta sequenceId:"29" a:b="c"] -"#,
+            r#"<1>1 - - - - - [meta sequenceId="29" a:b="c"] -"#,
+        ] {
+            let msg = parse_message_with_options(input, &opts).expect("should parse");
+            assert_eq!(msg.sd, expected.sd);
+            assert_eq!(
+                msg.sd.find_tuple("meta", "sequenceId").map(String::as_str),
+                Some("29")
+            );
+        }
+
+        let msg = parse_message_with_options(r#"<1>1 - - - - - [x a:"b c"] -"#, &opts).unwrap();
+        assert_eq!(msg.sd.find_tuple("x", "a").map(String::as_str), Some("b c"));
+
+        let err = parse_message(r#"<1>1 - - - - - [meta sequenceId:"29"] -"#)
+            .expect_err("strict mode requires =");
+        assert!(matches!(err.kind(), ParseErrKind::ExpectedTokenErr('=')));
+    }
+
+    #[cfg(not(feature = "strict-only"))]
+    #[test]
+    fn test_c_escapes() {
+        let input = r#"<1>1 - - - - - [meta a="one\ntwo" b="\t\q\]\\"] msg"#;
+        let msg = parse_message(input).expect("should parse");
+        assert_eq!(msg.sd.find_tuple("meta", "a").unwrap(), r"one\ntwo");
+        assert_eq!(msg.sd.find_tuple("meta", "b").unwrap(), r"\t\q]\");
+
+        let opts = ParseOptions {
+            allow_c_escapes: true,
+            ..Default::default()
+        };
+        let msg = parse_message_with_options(input, &opts).expect("should parse");
+        assert_eq!(msg.sd.find_tuple("meta", "a").unwrap(), "one\ntwo");
+        assert_eq!(msg.sd.find_tuple("meta", "b").unwrap(), "\t\\q]\\");
+    }
+
+    #[cfg(not(feature = "strict-only"))]
+    #[test]
+    fn test_space_before_version() {
+        let input = "<13> 1 2016-01-15T00:04:01Z host1 - - - - msg";
+        parse_message(input).expect_err("should require the version right after the PRI");
+
+        let opts = ParseOptions {
+            allow_space_before_version: true,
+            ..Default::default()
+        };
+        let msg = parse_message_with_options(input, &opts).expect("should parse");
+        assert_eq!(msg.version, 1);
+        assert_eq!(msg.hostname.as_deref(), Some("host1"));
+        assert_eq!(msg.msg, "msg");
+
+        for missing_version in &[
+            "<13> 2016-01-15T00:04:01Z host1 - - - - msg",
+            "<13> - host1 - - - - msg",
+        ] {
+            let err = parse_message_with_options(missing_version, &opts).expect_err("should fail");
+            assert!(
+                matches!(err.kind(), ParseErrKind::MissingField("version")),
+                "{:?}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_message_bytes() {
+        let msg =
+            parse_message_bytes(b"<78>1 - host1 app - - [meta x=\"1\"] \x1f\x8b\x08\x00").unwrap();
+        assert_eq!(msg.hostname.as_deref(), Some("host1"));
+        assert_eq!(msg.sd.find_tuple("meta", "x").unwrap(), "1");
+        assert_eq!(msg.msg, "\u{1f}\u{fffd}\u{8}\0");
+        assert_eq!(msg.msg_raw.as_deref(), Some(&b"\x1f\x8b\x08\x00"[..]));
+
+        // valid UTF-8 doesn't need a raw copy
+        let msg = parse_message_bytes("<78>1 - host1 - - - - café".as_bytes()).unwrap();
+        assert_eq!(msg.msg, "café");
+        assert_eq!(msg.msg_raw, None);
+
+        // the header still has to be text
+        let err = parse_message_bytes(b"<78>1 - h\xffst - - - - hi").unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::BaseUnicodeError(_)));
+        assert_eq!(err.offset(), 9);
+
+        let lossy = ParseOptions {
+            utf8_mode: Utf8Mode::LossyMsg,
+            ..Default::default()
+        };
+        for input in &[
+            &b"<78>1 - host1 app - - [meta x=\"1\"] \x1f\x8b\x08\x00"[..],
+            &b"<78>1 - host1 - - - - caf\xc3\xa9"[..],
+            &b"<1>1 - - - - - - \xef\xbb\xbf\xff"[..],
+        ] {
+            assert_eq!(
+                parse_message_bytes(input).unwrap(),
+                parse_message_bytes_with_options(input, &lossy).unwrap()
+            );
+        }
+        for input in &[&b"<78>1 - h\xffst - - - - hi"[..], &b"<78>1 -"[..]] {
+            let bytes_err = parse_message_bytes(input).unwrap_err();
+            let opts_err = parse_message_bytes_with_options(input, &lossy).unwrap_err();
+            assert_eq!(bytes_err.offset(), opts_err.offset());
+            assert_eq!(bytes_err.to_string(), opts_err.to_string());
+        }
     }
 
     #[test]
-    fn test_with_time_zulu() {
-        let msg = parse_message("<1>1 2015-01-01T00:00:00Z host - - - -")
-            .expect("Should parse empty message");
-        assert_eq!(msg.timestamp, Some(1420070400));
+    fn test_bytes_utf8_modes() {
+        let strict = ParseOptions::default();
+        let lossy = ParseOptions {
+            utf8_mode: Utf8Mode::LossyMsg,
+            ..Default::default()
+        };
+        let is_utf8_err = |e: ParseErr| matches!(e.kind(), ParseErrKind::BaseUnicodeError(_));
+
+        let valid = "<1>1 - host - - - [meta x=\"é\"] café".as_bytes();
+        for opts in &[&strict, &lossy] {
+            let msg = parse_message_bytes_with_options(valid, opts).expect("should parse");
+            assert_eq!(msg.sd.find_tuple("meta", "x").unwrap(), "é");
+            assert_eq!(msg.msg, "café");
+        }
+
+        let bad_msg = b"<1>1 - host - - - - caf\xe9 au lait";
+        assert!(is_utf8_err(
+            parse_message_bytes_with_options(bad_msg, &strict).unwrap_err()
+        ));
+        let msg = parse_message_bytes_with_options(bad_msg, &lossy).expect("should parse");
+        assert_eq!(msg.hostname.as_deref(), Some("host"));
+        assert_eq!(msg.msg, "caf\u{fffd} au lait");
+        assert_eq!(msg.msg_raw.as_deref(), Some(&b"caf\xe9 au lait"[..]));
+
+        // right after the SD, so still part of the MSG
+        let msg = parse_message_bytes_with_options(b"<1>1 - - - - - -\xff", &lossy)
+            .expect("should parse");
+        assert_eq!(msg.msg, "\u{fffd}");
+
+        for bad_header in &[
+            &b"<1>1 - h\xffst - - - - msg"[..],
+            &b"<1>1 - - - - - [meta x=\"\xff\"] msg"[..],
+            &b"<1>1 - - - - \xff msg"[..],
+        ] {
+            for opts in &[&strict, &lossy] {
+                let err = parse_message_bytes_with_options(bad_header, opts).unwrap_err();
+                assert!(is_utf8_err(err), "{:?}", bad_header);
+            }
+        }
+        #[cfg(not(feature = "strict-only"))]
+        {
+            let utf8_header = ParseOptions {
+                allow_utf8_in_header: true,
+                ..lossy.clone()
+            };
+            let err = parse_message_bytes_with_options(b"<1>1 - h\xffst - - - - msg", &utf8_header)
+                .unwrap_err();
+            assert!(is_utf8_err(err));
+        }
     }
 
+    #[cfg(not(feature = "strict-only"))]
     #[test]
-    fn test_with_time_offset() {
-        let msg = parse_message("<1>1 2015-01-01T00:00:00+00:00 - - - - -")
-            .expect("Should parse empty message");
-        assert_eq!(msg.timestamp, Some(1420070400));
+    fn test_trailer() {
+        let input = "<1>1 - - - - - - part one\x1fpart two\x1f1234abcd";
+        let msg = parse_message(input).expect("should parse");
+        assert_eq!(msg.msg, "part one\x1fpart two\x1f1234abcd");
+        assert_eq!(msg.trailer, None);
+
+        let opts = ParseOptions {
+            trailing_field_delimiter: Some('\x1f'),
+            ..Default::default()
+        };
+        let msg = parse_message_with_options(input, &opts).expect("should parse");
+        assert_eq!(msg.msg, "part one\x1fpart two");
+        assert_eq!(msg.trailer.as_deref(), Some("1234abcd"));
+
+        let msg =
+            parse_message_with_options("<1>1 - - - - - - no trailer", &opts).expect("should parse");
+        assert_eq!(msg.msg, "no trailer");
+        assert_eq!(msg.trailer, None);
     }
 
     #[test]
-    fn test_with_time_offset_nonzero() {
-        let msg = parse_message("<1>1 2015-01-01T00:00:00-10:00 - - - - -")
-            .expect("Should parse empty message");
-        assert_eq!(msg.timestamp, Some(1420106400));
-        // example from RFC 3339
-        let msg1 = parse_message("<1>1 2015-01-01T18:50:00-04:00 - - - - -")
-            .expect("Should parse empty message");
-        let msg2 = parse_message("<1>1 2015-01-01T22:50:00Z - - - - -")
-            .expect("Should parse empty message");
-        assert_eq!(msg1.timestamp, msg2.timestamp);
-        // example with fractional minutes
-        let msg1 = parse_message("<1>1 2019-01-20T00:46:39+05:45 - - - - -")
-            .expect("Should parse empty message");
-        let msg2 = parse_message("<1>1 2019-01-19T11:01:39-08:00 - - - - -")
-            .expect("Should parse empty message");
-        assert_eq!(msg1.timestamp, msg2.timestamp);
+    fn test_consumed() {
+        let opts = ParseOptions::default();
+        let buf = "<1>1 - - - - - - one\n<1>1 - - - - - [a b=\"c\"] two\n<1>1 - - - - - - three";
+        let mut rest = buf;
+        let mut bodies = Vec::new();
+        while !rest.is_empty() {
+            let (msg, consumed) = parse_message_consumed(rest, &opts).expect("should parse");
+            bodies.push(msg.msg);
+            rest = &rest[consumed..];
+        }
+        assert_eq!(bodies, vec!["one", "two", "three"]);
+
+        let (_, consumed) = parse_message_consumed("<1>1 - - - - - -\n", &opts).unwrap();
+        assert_eq!(consumed, 17);
+
+        let buf = "<1>1 - - - - - - one\r\n<1>1 - - - - - -\r\n";
+        let (msg, consumed) = parse_message_consumed(buf, &opts).unwrap();
+        assert_eq!(msg.msg, "one");
+        assert_eq!(consumed, 22);
+        let (msg, consumed) = parse_message_consumed(&buf[consumed..], &opts).unwrap();
+        assert_eq!(msg.msg, "");
+        assert_eq!(consumed, 18);
+
+        // error offsets don't depend on what follows the first message
+        let err = parse_message_consumed("<1>1\n<1>1 - - - - - -\n", &opts).unwrap_err();
+        assert_eq!(err.offset(), 4);
     }
 
+    #[cfg(feature = "base64")]
     #[test]
-    fn test_complex() {
-        let msg = parse_message("<78>1 2016-01-15T00:04:01+00:00 host1 CROND 10391 - [meta sequenceId=\"29\"] some_message").expect("Should parse complex message");
-        assert_eq!(msg.facility, SyslogFacility::LOG_CRON);
-        assert_eq!(msg.severity, SyslogSeverity::SEV_INFO);
-        assert_eq!(msg.hostname, Some(String::from("host1")));
-        assert_eq!(msg.appname, Some(String::from("CROND")));
-        assert_eq!(msg.procid, Some(message::ProcId::PID(10391)));
-        assert_eq!(msg.msg, String::from("some_message"));
-        assert_eq!(msg.timestamp, Some(1452816241));
-        assert_eq!(msg.sd.len(), 1);
-        let v = msg
-            .sd
-            .find_tuple("meta", "sequenceId")
-            .expect("Should contain meta sequenceId");
-        assert_eq!(v, "29");
+    fn test_base64_input() {
+        let opts = ParseOptions {
+            input_encoding: InputEncoding::Base64,
+            ..Default::default()
+        };
+        let wrapped = "PDc4PjEgMjAxNi0wMS0xNVQwMDowNDowMVogaG9zdDEgQ1JPTkQgMTAzOTEgLSBbbWV0YSBzZXF1ZW5jZUlkPSIyOSJdIGNhZsOp";
+        let msg = parse_message_with_options(wrapped, &opts).unwrap();
+        assert_eq!(msg.hostname.as_deref(), Some("host1"));
+        assert_eq!(msg.sd.find_tuple("meta", "sequenceId").unwrap(), "29");
+        assert_eq!(msg.msg, "caf\u{e9}");
+
+        // padding is optional
+        let msg = parse_message_bytes_with_options(b"PDE+MSAtIC0gLSAtIC0gLSBoaQ", &opts).unwrap();
+        assert_eq!(msg.msg, "hi");
+
+        let err =
+            parse_message_with_options("PDE+MSAtI*AtIC0gLSAtIC0gLSBoaQ==", &opts).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ParseErrKind::InvalidInputEncoding("base64")
+        ));
+        assert_eq!(err.offset(), 9);
+        // offsets in parse errors are into the decoded message
+        let err = parse_message_with_options("PDE+MSAtIC0gLSAtIC0=", &opts).unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::UnexpectedEndOfInput));
+        assert_eq!(err.offset(), 14);
     }
 
     #[test]
-    fn test_sd_empty() {
-        let msg = parse_message(
-            "<78>1 2016-01-15T00:04:01Z host1 CROND 10391 - [meta@1234] some_message",
+    fn test_percent_input() {
+        let opts = ParseOptions {
+            input_encoding: InputEncoding::Percent,
+            ..Default::default()
+        };
+        let msg = parse_message_with_options(
+            "%3C78%3E1%20-%20host1%20-%20-%20-%20%5Bmeta%20x=%221%22%5D%20a+b%25c%c3%A9",
+            &opts,
         )
-        .expect("Should parse message with empty structured data");
-        assert_eq!(msg.facility, SyslogFacility::LOG_CRON);
-        assert_eq!(msg.severity, SyslogSeverity::SEV_INFO);
-        assert_eq!(msg.hostname, Some(String::from("host1")));
-        assert_eq!(msg.appname, Some(String::from("CROND")));
-        assert_eq!(msg.procid, Some(message::ProcId::PID(10391)));
-        assert_eq!(msg.msg, String::from("some_message"));
-        assert_eq!(msg.timestamp, Some(1452816241));
-        assert_eq!(msg.sd.len(), 1);
+        .unwrap();
+        assert_eq!(msg.hostname.as_deref(), Some("host1"));
+        assert_eq!(msg.sd.find_tuple("meta", "x").unwrap(), "1");
+        assert_eq!(msg.msg, "a+b%c\u{e9}");
+        // unencoded text passes through unchanged
         assert_eq!(
-            msg.sd
-                .find_sdid("meta@1234")
-                .expect("should contain meta")
-                .len(),
-            0
+            parse_message_with_options("<1>1 - - - - - - hi", &opts)
+                .unwrap()
+                .msg,
+            "hi"
         );
+
+        for (input, offset) in &[("<1>1 - - - - - - 100%", 20), ("%3C1%3g", 4), ("%", 0)] {
+            let err = parse_message_with_options(input, &opts).unwrap_err();
+            assert!(matches!(
+                err.kind(),
+                ParseErrKind::InvalidInputEncoding("percent-encoding")
+            ));
+            assert_eq!(err.offset(), *offset, "{}", input);
+        }
+        let err = parse_message_with_options("<1>1 - - - - - - %ff", &opts).unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::UnicodeError(_)));
     }
 
     #[test]
-    fn test_sd_features() {
-        let msg = parse_message("<78>1 2016-01-15T00:04:01Z host1 CROND 10391 - [meta sequenceId=\"29\" sequenceBlah=\"foo\"][my key=\"value\"][meta bar=\"baz=\"] some_message").expect("Should parse complex message");
-        assert_eq!(msg.facility, SyslogFacility::LOG_CRON);
-        assert_eq!(msg.severity, SyslogSeverity::SEV_INFO);
-        assert_eq!(msg.hostname, Some(String::from("host1")));
-        assert_eq!(msg.appname, Some(String::from("CROND")));
-        assert_eq!(msg.procid, Some(message::ProcId::PID(10391)));
-        assert_eq!(msg.msg, String::from("some_message"));
-        assert_eq!(msg.timestamp, Some(1452816241));
-        assert_eq!(msg.sd.len(), 2);
-        assert_eq!(
-            msg.sd.find_sdid("meta").expect("should contain meta").len(),
-            3
-        );
+    fn test_octet_counted() {
+        let buf = "16 <1>1 - - - - - -22 <1>1 - - - - - - hello";
+        let (msg, rest) = parse_octet_counted(buf).unwrap();
+        assert_eq!(msg.msg, "");
+        let (msg, rest) = parse_octet_counted(rest).unwrap();
+        assert_eq!(msg.msg, "hello");
+        assert_eq!(rest, "");
+
+        for (input, kind, offset) in &[
+            ("23 <1>1 - - - - - - hello", "UnexpectedEndOfInput", 25),
+            ("16", "UnexpectedEndOfInput", 2),
+            ("", "InvalidOctetCount", 0),
+            ("<1>1 - - - - - -", "InvalidOctetCount", 0),
+            ("016 <1>1 - - - - - -", "InvalidOctetCount", 0),
+            ("16x<1>1 - - - - - -", "InvalidOctetCount", 2),
+            ("99999999999999999999999 <1>", "InvalidOctetCount", 0),
+            ("5 <1>1 - - - - - -", "MissingField", 7),
+            ("4 <1>\u{e9}", "InvalidOctetCount", 0),
+        ] {
+            let err = parse_octet_counted(input).expect_err(input);
+            assert!(format!("{:?}", err.kind()).starts_with(kind), "{:?}", err);
+            assert_eq!(err.offset(), *offset, "{:?} for {:?}", err, input);
+        }
     }
 
     #[test]
-    fn test_sd_with_escaped_quote() {
-        let msg_text = r#"<1>1 - - - - - [meta key="val\"ue"] message"#;
-        let msg = parse_message(msg_text).expect("should parse");
+    fn test_parse_messages() {
+        let buf = "16 <1>1 - - - - - -22 <1>1 - - - - - - hello";
+        let (messages, consumed, err) = parse_messages(buf);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(consumed, buf.len());
+        assert!(err.is_none());
+
+        // stops cleanly before a partial frame, wherever it was cut off
+        for cut in 20..buf.len() {
+            let (messages, consumed, err) = parse_messages(&buf[..cut]);
+            assert_eq!(messages.len(), 1, "{}", cut);
+            assert_eq!(consumed, 19);
+            assert!(err.is_none());
+        }
+        let (messages, consumed, err) = parse_messages("");
+        assert!(messages.is_empty() && consumed == 0 && err.is_none());
+
+        // a bad frame ends it, but keeps what came before
+        let buf = "16 <1>1 - - - - - -5 <1>1 - - - - - -";
+        let (messages, consumed, err) = parse_messages(buf);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(consumed, 19);
+        let err = err.expect("the second frame is too short");
+        assert!(matches!(err.kind(), ParseErrKind::MissingField(_)));
+        assert_eq!(err.offset(), 26);
+
+        let (messages, consumed, err) = parse_messages("16 <1>1 - - - - - -x");
+        assert_eq!((messages.len(), consumed), (1, 19));
+        assert!(matches!(
+            err.unwrap().kind(),
+            ParseErrKind::InvalidOctetCount
+        ));
+    }
+
+    #[test]
+    fn test_structured_data_only() {
+        let input =
+            r#"<1>1 2016-01-15T00:04:01Z host1 app 1234 ID47 [meta a="1"][other b="2"] body"#;
+        let sd = parse_structured_data_only(input).expect("should parse");
+        assert_eq!(sd, parse_message(input).unwrap().sd);
+        assert_eq!(sd.find_tuple("other", "b").unwrap(), "2");
+
+        assert!(parse_structured_data_only("<1>1 - - - - - -")
+            .expect("should parse")
+            .is_empty());
+        parse_structured_data_only("<1>1 2016-13-15T00:04:01Z - - - - [a b=\"c\"]")
+            .expect_err("the header is still validated");
+    }
+
+    #[derive(Default)]
+    struct RecordingHandler(Vec<String>);
+
+    impl ParseHandler for RecordingHandler {
+        fn on_priority(&mut self, facility: SyslogFacility, severity: SyslogSeverity) {
+            self.0.push(format!("pri {:?} {:?}", facility, severity));
+        }
+
+        fn on_timestamp(&mut self, timestamp: Option<time::OffsetDateTime>) {
+            self.0
+                .push(format!("ts {:?}", timestamp.map(|t| t.unix_timestamp())));
+        }
+
+        fn on_hostname(&mut self, hostname: Option<&str>) {
+            self.0.push(format!("host {:?}", hostname));
+        }
+
+        fn on_msgid(&mut self, msgid: Option<&str>) {
+            self.0.push(format!("msgid {:?}", msgid));
+        }
+
+        fn on_sd_element(&mut self, sd_id: &str) {
+            self.0.push(format!("sd {}", sd_id));
+        }
+
+        fn on_sd_param(&mut self, sd_id: &str, name: &str, value: &str) {
+            self.0.push(format!("param {} {}={}", sd_id, name, value));
+        }
+
+        fn on_message(&mut self, msg: &str) {
+            self.0.push(format!("msg {}", msg));
+        }
+    }
+
+    #[test]
+    fn test_parse_sax() {
+        let mut handler = RecordingHandler::default();
+        parse_sax(
+            r#"<78>1 2016-01-15T00:04:01Z host1 CROND 10391 - [meta sequenceId="29" x="a\"b" x="c"][empty] some_message"#,
+            &mut handler,
+        )
+        .expect("should parse");
         assert_eq!(
-            msg.sd
-                .find_tuple("meta", "key")
-                .expect("Should contain meta key"),
-            r#"val"ue"#
+            handler.0,
+            [
+                "pri LOG_CRON SEV_INFO",
+                "ts Some(1452816241)",
+                "host Some(\"host1\")",
+                "msgid None",
+                "sd meta",
+                "param meta sequenceId=29",
+                r#"param meta x=a"b"#,
+                "param meta x=c",
+                "sd empty",
+                "msg some_message",
+            ]
         );
+
+        let mut handler = RecordingHandler::default();
+        let err = parse_sax(r#"<1>1 - - - - - [a b="c"][d"#, &mut handler).unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::UnexpectedEndOfInput));
+        // everything before the error was still reported
+        assert_eq!(handler.0.last().map(String::as_str), Some("param a b=c"));
+
+        let mut handler = RecordingHandler::default();
+        parse_sax(
+            "<1>1 - host1 - - abcdefghijklmnopqrstuvwxyz0123456789 -",
+            &mut handler,
+        )
+        .unwrap_err();
+        // but the header is reported all at once
+        assert!(handler.0.is_empty());
     }
 
     #[test]
-    fn test_other_message() {
-        let msg_text = r#"<190>1 2016-02-21T01:19:11+00:00 batch6sj - - - [meta sequenceId="21881798" x-group="37051387"][origin x-service="tracking"] metascutellar conversationalist nephralgic exogenetic graphy streng outtaken acouasm amateurism prenotice Lyonese bedull antigrammatical diosphenol gastriloquial bayoneteer sweetener naggy roughhouser dighter addend sulphacid uneffectless ferroprussiate reveal Mazdaist plaudite Australasian distributival wiseman rumness Seidel topazine shahdom sinsion mesmerically pinguedinous ophthalmotonometer scuppler wound eciliate expectedly carriwitchet dictatorialism bindweb pyelitic idic atule kokoon poultryproof rusticial seedlip nitrosate splenadenoma holobenthic uneternal Phocaean epigenic doubtlessly indirection torticollar robomb adoptedly outspeak wappenschawing talalgia Goop domitic savola unstrafed carded unmagnified mythologically orchester obliteration imperialine undisobeyed galvanoplastical cycloplegia quinquennia foremean umbonal marcgraviaceous happenstance theoretical necropoles wayworn Igbira pseudoangelic raising unfrounced lamasary centaurial Japanolatry microlepidoptera"#;
-        parse_message(msg_text).expect("should parse as text");
+    fn test_leap_second() {
+        let opts = ParseOptions::default();
+        let (msg, warnings) =
+            parse_message_with_warnings("<1>1 1990-12-31T23:59:60Z - - - - -", &opts)
+                .expect("should parse");
+        assert_eq!(msg.timestamp, Some(662687999));
+        assert_eq!(msg.timestamp_nanos, Some(999_999_000));
+        assert_eq!(warnings, vec![ParseWarning::LeapSecond]);
+
+        // what is written back is still strict RFC 5424, and parses to the same message
+        let written = msg.to_rfc5424_string();
+        assert_eq!(written, "<1>1 1990-12-31T23:59:59.999999Z - - - - -");
+        assert_eq!(parse_message(&written).expect("should re-parse"), msg);
+
+        // the fraction and offset don't change where it lands
+        for input in &[
+            "<1>1 1990-12-31T23:59:60.5Z - - - - -",
+            "<1>1 1990-12-31T15:59:60-08:00 - - - - -",
+        ] {
+            let msg = parse_message(input).unwrap();
+            assert_eq!(msg.timestamp_timespec(), Some((662687999, 999_999_000)));
+        }
+
+        let (_, warnings) =
+            parse_message_with_warnings("<1>1 1990-12-31T23:59:59Z - - - - -", &opts).unwrap();
+        assert!(warnings.is_empty());
+        let err = parse_message("<1>1 1990-12-31T23:59:61Z - - - - -").unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::InvalidDate(_)));
     }
 
     #[test]
-    fn test_bad_pri() {
-        let msg = parse_message("<4096>1 - - - - - -");
-        assert!(msg.is_err());
+    fn test_trailing_whitespace_warning() {
+        let opts = ParseOptions::default();
+        let (msg, warnings) =
+            parse_message_with_warnings("<1>1 - - - - - - message  ", &opts).expect("should parse");
+        assert_eq!(msg.msg, "message  ");
+        assert_eq!(warnings, vec![ParseWarning::TrailingWhitespace]);
+
+        let (_, warnings) =
+            parse_message_with_warnings("<1>1 - - - - - - message\n", &opts).expect("should parse");
+        assert_eq!(warnings, vec![ParseWarning::TrailingWhitespace]);
+
+        for input in &[
+            "<1>1 - - - - - - message",
+            "<1>1 - - - - - -",
+            "<1>1 - - - - - - ",
+        ] {
+            let (_, warnings) = parse_message_with_warnings(input, &opts).expect("should parse");
+            assert!(warnings.is_empty(), "{:?}", input);
+        }
     }
 
+    #[cfg(not(feature = "strict-only"))]
     #[test]
-    fn test_bad_match() {
-        // we shouldn't be able to parse RFC3164 messages
-        let msg = parse_message("<134>Feb 18 20:53:31 haproxy[376]: I am a message");
-        assert!(msg.is_err());
+    fn test_long_fractional_seconds() {
+        let input = "<1>1 2018-12-31T23:59:59.9999999995Z - - - - -";
+        parse_message(input).expect_err("more than 6 digits are rejected by default");
+
+        let truncate = ParseOptions {
+            allow_long_fractional_seconds: true,
+            ..Default::default()
+        };
+        let msg = parse_message_with_options(input, &truncate).expect("should parse");
+        assert_eq!(msg.timestamp, Some(1546300799));
+        assert_eq!(msg.timestamp_nanos, Some(999_999_999));
+
+        let half_up = ParseOptions {
+            fractional_rounding: Rounding::HalfUp,
+            ..truncate.clone()
+        };
+        let msg = parse_message_with_options(input, &half_up).expect("should parse");
+        assert_eq!(msg.timestamp, Some(1546300800));
+        assert_eq!(msg.timestamp_nanos, Some(0));
+
+        let msg =
+            parse_message_with_options("<1>1 2018-12-31T23:59:59.1234567894Z - - - - -", &half_up)
+                .expect("should parse");
+        assert_eq!(msg.timestamp_nanos, Some(123_456_789));
+
+        let msg = parse_message_with_options(
+            "<1>1 2018-12-31T23:59:59.1234567+01:00 - - - - -",
+            &truncate,
+        )
+        .expect("should parse");
+        assert_eq!(msg.timestamp, Some(1546297199));
+        assert_eq!(msg.timestamp_nanos, Some(123_456_700));
     }
 
+    #[cfg(not(feature = "strict-only"))]
     #[test]
-    fn test_example_timestamps() {
-        // these are the example timestamps in the rfc
+    fn test_max_frac_digits() {
+        let opts = ParseOptions {
+            max_frac_digits: Some(9),
+            ..Default::default()
+        };
+        let msg =
+            parse_message_with_options("<1>1 2018-12-31T23:59:59.123456789Z - - - - -", &opts)
+                .expect("should parse");
+        assert_eq!(msg.timestamp_nanos, Some(123_456_789));
+        let msg = parse_message_with_options("<1>1 2018-12-31T23:59:59.5Z - - - - -", &opts)
+            .expect("should parse");
+        assert_eq!(msg.timestamp_nanos, Some(500_000_000));
 
-        let msg = parse_message("<1>1 1985-04-12T23:20:50.52Z host - - - -")
-            .expect("Should parse empty message");
-        assert_eq!(msg.timestamp, Some(482196050));
-        assert_eq!(msg.timestamp_nanos, Some(520000000));
+        let msg =
+            parse_message_with_options("<1>1 2018-12-31T23:59:59.1234567890Z - - - - -", &opts)
+                .expect("extra digits are dropped");
+        assert_eq!(msg.timestamp_nanos, Some(123_456_789));
 
-        let msg = parse_message("<1>1 1985-04-12T19:20:50.52+04:00 host - - - -")
-            .expect("Should parse empty message");
-        assert_eq!(msg.timestamp, Some(482167250));
-        assert_eq!(msg.timestamp_nanos, Some(520000000));
+        let opts = ParseOptions {
+            max_frac_digits: Some(3),
+            // max_frac_digits wins
+            allow_long_fractional_seconds: true,
+            ..Default::default()
+        };
+        let msg = parse_message_with_options("<1>1 2018-12-31T23:59:59.123999Z - - - - -", &opts)
+            .expect("should parse");
+        assert_eq!(msg.timestamp_nanos, Some(123_000_000));
 
-        let msg = parse_message("<1>1 1985-04-12T19:20:50+04:00 host - - - -")
-            .expect("Should parse empty message");
-        assert_eq!(msg.timestamp, Some(482167250));
+        let opts = ParseOptions {
+            max_frac_digits: Some(12),
+            fractional_rounding: Rounding::HalfUp,
+            ..Default::default()
+        };
+        let msg =
+            parse_message_with_options("<1>1 2018-12-31T23:59:59.123456789500Z - - - - -", &opts)
+                .expect("should parse");
+        assert_eq!(msg.timestamp_nanos, Some(123_456_790));
+
+        let opts = ParseOptions {
+            max_frac_digits: Some(2),
+            fractional_rounding: Rounding::HalfUp,
+            ..Default::default()
+        };
+        let msg = parse_message_with_options("<1>1 2018-12-31T23:59:59.125Z - - - - -", &opts)
+            .expect("should parse");
+        assert_eq!(msg.timestamp_nanos, Some(130_000_000));
+        let msg = parse_message_with_options("<1>1 2018-12-31T23:59:59.996Z - - - - -", &opts)
+            .expect("should parse");
+        assert_eq!(msg.timestamp, Some(1546300800));
         assert_eq!(msg.timestamp_nanos, Some(0));
+    }
 
-        let msg = parse_message("<1>1 2003-08-24T05:14:15.000003+07:00 host - - - -")
-            .expect("Should parse empty message");
-        assert_eq!(msg.timestamp, Some(1061676855));
-        assert_eq!(msg.timestamp_nanos, Some(3000));
+    #[cfg(not(feature = "strict-only"))]
+    #[test]
+    fn test_empty_fraction() {
+        let opts = ParseOptions {
+            allow_empty_fraction: true,
+            ..Default::default()
+        };
+        for input in &[
+            "<1>1 1985-04-12T23:20:50.Z - - - - -",
+            "<1>1 1985-04-12T23:20:50.-Z - - - - -",
+        ] {
+            parse_message(input).expect_err("an empty fraction is invalid by default");
+            let msg = parse_message_with_options(input, &opts).expect("should parse");
+            assert_eq!(msg.timestamp, Some(482196050));
+            assert_eq!(msg.timestamp_nanos, Some(0));
+        }
 
-        let msg = parse_message("<1>1 2003-08-24T05:14:15.000000003+07:00 host - - - -");
-        assert!(msg.is_err(), "expected parse fail");
+        let msg = parse_message_with_options("<1>1 1985-04-12T23:20:50.-01:00 - - - - -", &opts)
+            .expect("should parse");
+        assert_eq!(msg.timestamp, Some(482199650));
+        let msg = parse_message_with_options("<1>1 1985-04-12T23:20:50.--01:00 - - - - -", &opts)
+            .expect("should parse");
+        assert_eq!(msg.timestamp, Some(482199650));
+        let msg = parse_message_with_options("<1>1 1985-04-12T23:20:50.5Z - - - - -", &opts)
+            .expect("should parse");
+        assert_eq!(msg.timestamp_nanos, Some(500_000_000));
     }
 
+    #[cfg(not(feature = "strict-only"))]
     #[test]
-    fn test_empty_sd_value() {
-        let msg = parse_message(r#"<29>1 2018-05-14T08:23:01.520Z leyal_test4 mgd 13894 UI_CHILD_EXITED [junos@2636.1.1.1.2.57 pid="14374" return-value="5" core-dump-status="" command="/usr/sbin/mustd"]"#).expect("must parse");
-        assert_eq!(msg.facility, SyslogFacility::LOG_DAEMON);
+    fn test_pri_zero_as_default() {
+        let msg = parse_message("<0>1 - - - - - -").expect("should parse");
+        assert_eq!(msg.facility, SyslogFacility::LOG_KERN);
+        assert_eq!(msg.severity, SyslogSeverity::SEV_EMERG);
+
+        let mut opts = ParseOptions {
+            pri_zero_as_default: true,
+            ..Default::default()
+        };
+        let msg = parse_message_with_options("<0>1 - - - - - -", &opts).expect("should parse");
+        assert_eq!(msg.facility, SyslogFacility::LOG_USER);
         assert_eq!(msg.severity, SyslogSeverity::SEV_NOTICE);
-        assert_eq!(msg.hostname, Some(String::from("leyal_test4")));
-        assert_eq!(msg.appname, Some(String::from("mgd")));
-        assert_eq!(msg.procid, Some(message::ProcId::PID(13894)));
-        assert_eq!(msg.msg, String::from(""));
-        assert_eq!(msg.timestamp, Some(1526286181));
-        assert_eq!(msg.timestamp_nanos, Some(520000000));
-        assert_eq!(msg.sd.len(), 1);
-        let sd = msg
-            .sd
-            .find_sdid("junos@2636.1.1.1.2.57")
-            .expect("should contain root SD");
-        let expected = {
-            let mut expected = BTreeMap::new();
-            expected.insert("pid", "14374");
-            expected.insert("return-value", "5");
-            expected.insert("core-dump-status", "");
-            expected.insert("command", "/usr/sbin/mustd");
-            expected
-                .into_iter()
-                .map(|(k, v)| (k.to_string(), v.to_string()))
-                .collect::<BTreeMap<_, _>>()
+
+        opts.default_facility = Some(SyslogFacility::LOG_LOCAL3);
+        opts.default_severity = Some(SyslogSeverity::SEV_INFO);
+        let msg = parse_message_with_options("<0>1 - - - - - -", &opts).expect("should parse");
+        assert_eq!(msg.facility, SyslogFacility::LOG_LOCAL3);
+        assert_eq!(msg.severity, SyslogSeverity::SEV_INFO);
+
+        // only exactly zero is special
+        let msg = parse_message_with_options("<8>1 - - - - - -", &opts).expect("should parse");
+        assert_eq!(msg.facility, SyslogFacility::LOG_USER);
+        assert_eq!(msg.severity, SyslogSeverity::SEV_EMERG);
+    }
+
+    #[cfg(not(feature = "strict-only"))]
+    #[test]
+    fn test_epoch_timestamp() {
+        let input = "<1>1 1452816241 host1 - - - - msg";
+        parse_message(input).expect_err("should require an RFC 3339 timestamp");
+
+        let opts = ParseOptions {
+            allow_epoch_timestamp: true,
+            ..Default::default()
         };
-        assert_eq!(sd, &expected);
+        let msg = parse_message_with_options(input, &opts).expect("should parse");
+        assert_eq!(msg.timestamp, Some(1452816241));
+        assert_eq!(msg.timestamp_nanos, Some(0));
+        assert_eq!(msg.hostname.as_deref(), Some("host1"));
+
+        let msg = parse_message_with_options("<1>1 2016-01-15T00:04:01Z host1 - - - - msg", &opts)
+            .expect("should parse");
+        assert_eq!(msg.timestamp, Some(1452816241));
+        parse_message_with_options("<1>1 1452816241x host1 - - - - msg", &opts)
+            .expect_err("should fail");
+        parse_message_with_options("<1>1 14528 host1 - - - - msg", &opts).expect_err("should fail");
     }
 
     #[test]
-    fn test_fields_start_with_dash() {
-        let msg = parse_message("<39>1 2018-05-15T20:56:58+00:00 -web1west -201805020050-bc5d6a47c3-master - - [meta sequenceId=\"28485532\"] 25450-uWSGI worker 6: getaddrinfo*.gaih_getanswer: got type \"DNAME\"").expect("should parse");
-        assert_eq!(msg.hostname, Some("-web1west".to_string()));
+    fn test_max_steps() {
+        let msg_text = format!(
+            r#"<1>1 - host app - - [meta key="{}"] message"#,
+            r#"\""#.repeat(1000)
+        );
+        let msg = parse_message(&msg_text).expect("should parse without a step limit");
         assert_eq!(
-            msg.appname,
-            Some("-201805020050-bc5d6a47c3-master".to_string())
+            msg.sd.find_tuple("meta", "key").unwrap(),
+            &"\"".repeat(1000)
         );
+
+        let opts = ParseOptions {
+            max_steps: Some(500),
+            ..Default::default()
+        };
+        let err = parse_message_with_options(&msg_text, &opts).expect_err("should hit the limit");
         assert_eq!(
-            msg.sd.find_tuple("meta", "sequenceId"),
-            Some(&"28485532".to_string())
+            mem::discriminant(err.kind()),
+            mem::discriminant(&ParseErrKind::StepLimitExceeded)
+        );
+
+        let opts = ParseOptions {
+            max_steps: Some(msg_text.len()),
+            ..Default::default()
+        };
+        parse_message_with_options(&msg_text, &opts).expect("should parse within the limit");
+    }
+
+    #[test]
+    fn test_offset_secs() {
+        let utc = parse_message("<1>1 2015-01-01T05:45:00Z - - - - -").unwrap();
+        assert_eq!(utc.timestamp_offset_secs, Some(0));
+        let nepal = parse_message("<1>1 2015-01-01T11:30:00+05:45 - - - - -").unwrap();
+        assert_eq!(nepal.timestamp_offset_secs, Some(5 * 3600 + 45 * 60));
+        assert_eq!(nepal.timestamp, utc.timestamp);
+        let msg = parse_message("<1>1 2015-01-01T00:00:00.5-03:30 - - - - -").unwrap();
+        assert_eq!(msg.timestamp_offset_secs, Some(-(3 * 3600 + 30 * 60)));
+        let msg = parse_message("<1>1 - - - - - -").unwrap();
+        assert_eq!(msg.timestamp_offset_secs, None);
+    }
+
+    #[test]
+    fn test_unknown_offset() {
+        let msg = parse_message("<1>1 2015-01-01T00:00:00Z - - - - -").expect("should parse");
+        assert_eq!(msg.timestamp, Some(1420070400));
+        assert!(!msg.timestamp_offset_unknown);
+        let msg = parse_message("<1>1 2015-01-01T00:00:00+00:00 - - - - -").expect("should parse");
+        assert_eq!(msg.timestamp, Some(1420070400));
+        assert!(!msg.timestamp_offset_unknown);
+        let msg = parse_message("<1>1 2015-01-01T00:00:00-00:00 - - - - -").expect("should parse");
+        assert_eq!(msg.timestamp, Some(1420070400));
+        assert!(msg.timestamp_offset_unknown);
+        let msg = parse_message("<1>1 2015-01-01T00:00:00-01:00 - - - - -").expect("should parse");
+        assert!(!msg.timestamp_offset_unknown);
+        let msg = parse_message("<1>1 - - - - - -").expect("should parse");
+        assert!(!msg.timestamp_offset_unknown);
+    }
+
+    #[test]
+    fn test_from_priority() {
+        assert_eq!(
+            from_priority(0).unwrap(),
+            (SyslogFacility::LOG_KERN, SyslogSeverity::SEV_EMERG)
         );
         assert_eq!(
-            msg.msg,
-            "25450-uWSGI worker 6: getaddrinfo*.gaih_getanswer: got type \"DNAME\"".to_string()
+            from_priority(191).unwrap(),
+            (SyslogFacility::LOG_LOCAL7, SyslogSeverity::SEV_DEBUG)
         );
+        let err = from_priority(192).unwrap_err();
+        assert!(matches!(err.kind(), ParseErrKind::BadFacilityInPri));
+        assert_eq!(err.offset(), 0);
+        assert!(from_priority(-1).is_err());
     }
 
+    #[cfg(not(feature = "strict-only"))]
     #[test]
-    fn test_truncated() {
-        let err =
-            parse_message("<39>1 2018-05-15T20:56:58+00:00 -web1west -").expect_err("should fail");
+    fn test_swapped_pri() {
+        // mail.info, encoded as (6 << 3) | 2
+        let msg = parse_message("<50>1 - - - - - -").expect("should parse");
+        assert_eq!(msg.facility, SyslogFacility::LOG_LPR);
+        assert_eq!(msg.severity, SyslogSeverity::SEV_CRIT);
+
+        let opts = ParseOptions {
+            swapped_pri: true,
+            ..Default::default()
+        };
+        let msg = parse_message_with_options("<50>1 - - - - - -", &opts).expect("should parse");
+        assert_eq!(msg.facility, SyslogFacility::LOG_MAIL);
+        assert_eq!(msg.severity, SyslogSeverity::SEV_INFO);
+
+        let err = parse_message_with_options("<64>1 - - - - - -", &opts).expect_err("should fail");
         assert_eq!(
-            mem::discriminant(&err),
-            mem::discriminant(&ParseErr::UnexpectedEndOfInput)
+            mem::discriminant(err.kind()),
+            mem::discriminant(&ParseErrKind::BadSeverityInPri)
         );
     }
 }
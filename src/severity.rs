@@ -1,11 +1,13 @@
-use std::convert::TryFrom;
+use core::convert::TryFrom;
+use core::fmt;
+use core::str::FromStr;
 
 #[cfg(feature = "serde-serialize")]
-use serde::{Serialize, Serializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 use thiserror::Error;
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[allow(non_camel_case_types)]
 /// Syslog Severities from RFC 5424.
 pub enum SyslogSeverity {
@@ -19,10 +21,12 @@ pub enum SyslogSeverity {
     SEV_DEBUG = 7,
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Error, PartialEq, Eq)]
 pub enum SyslogSeverityError {
     #[error("integer does not correspond to a known severity")]
     InvalidInteger,
+    #[error("string does not correspond to a known severity")]
+    InvalidName,
 }
 
 impl TryFrom<i32> for SyslogSeverity {
@@ -44,6 +48,18 @@ impl TryFrom<i32> for SyslogSeverity {
     }
 }
 
+impl FromStr for SyslogSeverity {
+    type Err = SyslogSeverityError;
+
+    /// Parse one of the names produced by `as_str` (such as `"warning"`), ignoring ASCII case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        (0..=7)
+            .filter_map(Self::from_int)
+            .find(|sev| sev.as_str().eq_ignore_ascii_case(s))
+            .ok_or(SyslogSeverityError::InvalidName)
+    }
+}
+
 impl SyslogSeverity {
     /// Convert an int (as used in the wire serialization) into a `SyslogSeverity`
     ///
@@ -53,6 +69,11 @@ impl SyslogSeverity {
         Self::try_from(i).ok()
     }
 
+    /// The severity's numeric code, as used in the PRI
+    pub fn as_int(self) -> i32 {
+        self as i32
+    }
+
     /// Convert a syslog severity into a unique string representation
     pub fn as_str(self) -> &'static str {
         match self {
@@ -66,6 +87,90 @@ impl SyslogSeverity {
             SyslogSeverity::SEV_DEBUG => "debug",
         }
     }
+
+    /// Whether `self` is at least as severe as `other`
+    ///
+    /// Note that this is the reverse of the derived `Ord`, which compares the numeric values:
+    /// `SEV_EMERG` (0) sorts *before* `SEV_DEBUG` (7) even though it's far more severe. So
+    /// `a.is_at_least(b)` is `a <= b`, and keeping only messages at `warning` or above is
+    ///
+    /// ```
+    /// use syslog_rfc5424::SyslogSeverity;
+    ///
+    /// let keep = |sev: SyslogSeverity| sev.is_at_least(SyslogSeverity::SEV_WARNING);
+    ///
+    /// assert!(keep(SyslogSeverity::SEV_CRIT));
+    /// assert!(keep(SyslogSeverity::SEV_WARNING));
+    /// assert!(!keep(SyslogSeverity::SEV_INFO));
+    /// ```
+    pub fn is_at_least(self, other: SyslogSeverity) -> bool {
+        self <= other
+    }
+
+    /// A human-readable description, as given in Table 2 of RFC 5424
+    pub fn description(self) -> &'static str {
+        match self {
+            SyslogSeverity::SEV_EMERG => "Emergency: system is unusable",
+            SyslogSeverity::SEV_ALERT => "Alert: action must be taken immediately",
+            SyslogSeverity::SEV_CRIT => "Critical: critical conditions",
+            SyslogSeverity::SEV_ERR => "Error: error conditions",
+            SyslogSeverity::SEV_WARNING => "Warning: warning conditions",
+            SyslogSeverity::SEV_NOTICE => "Notice: normal but significant condition",
+            SyslogSeverity::SEV_INFO => "Informational: informational messages",
+            SyslogSeverity::SEV_DEBUG => "Debug: debug-level messages",
+        }
+    }
+}
+
+/// Map a [`log::Level`] onto the syslog severity it should be emitted at.
+///
+/// `Trace` has no syslog equivalent and is folded into `SEV_DEBUG`.
+#[cfg(feature = "log")]
+pub fn severity_from_log_level(level: log::Level) -> SyslogSeverity {
+    match level {
+        log::Level::Error => SyslogSeverity::SEV_ERR,
+        log::Level::Warn => SyslogSeverity::SEV_WARNING,
+        log::Level::Info => SyslogSeverity::SEV_INFO,
+        log::Level::Debug | log::Level::Trace => SyslogSeverity::SEV_DEBUG,
+    }
+}
+
+/// Map a [`tracing::Level`] onto the syslog severity it should be emitted at.
+///
+/// `TRACE` has no syslog equivalent and is folded into `SEV_DEBUG`.
+#[cfg(feature = "tracing")]
+pub fn severity_from_tracing_level(level: tracing::Level) -> SyslogSeverity {
+    match level {
+        tracing::Level::ERROR => SyslogSeverity::SEV_ERR,
+        tracing::Level::WARN => SyslogSeverity::SEV_WARNING,
+        tracing::Level::INFO => SyslogSeverity::SEV_INFO,
+        _ => SyslogSeverity::SEV_DEBUG,
+    }
+}
+
+impl PartialEq<str> for SyslogSeverity {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for SyslogSeverity {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Display for SyslogSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for SyslogSeverity {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self::from_int(u.int_in_range(0..=7)?).expect("all of 0..=7 are severities"))
+    }
 }
 
 #[cfg(feature = "serde-serialize")]
@@ -75,9 +180,20 @@ impl Serialize for SyslogSeverity {
     }
 }
 
+#[cfg(feature = "serde-serialize")]
+impl<'de> Deserialize<'de> for SyslogSeverity {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        alloc::string::String::deserialize(de)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::SyslogSeverity;
+    use std::str::FromStr;
+
+    use super::{SyslogSeverity, SyslogSeverityError};
 
     #[test]
     fn test_deref() {
@@ -90,4 +206,112 @@ mod tests {
         assert_eq!(SyslogSeverity::SEV_INFO.as_str(), "info");
         assert_eq!(SyslogSeverity::SEV_DEBUG.as_str(), "debug");
     }
+
+    #[test]
+    fn test_as_int() {
+        for i in 0..=7 {
+            assert_eq!(SyslogSeverity::from_int(i).unwrap().as_int(), i);
+        }
+    }
+
+    #[test]
+    fn test_description() {
+        assert_eq!(
+            SyslogSeverity::SEV_EMERG.description(),
+            "Emergency: system is unusable"
+        );
+        assert_eq!(
+            SyslogSeverity::SEV_DEBUG.description(),
+            "Debug: debug-level messages"
+        );
+    }
+
+    #[test]
+    fn test_str_eq() {
+        assert_eq!(SyslogSeverity::SEV_WARNING, "warning");
+        assert!(SyslogSeverity::SEV_WARNING == *"warning");
+        assert_ne!(SyslogSeverity::SEV_WARNING, "SEV_WARNING");
+    }
+
+    #[test]
+    fn test_display() {
+        for sev in (0..=7).filter_map(SyslogSeverity::from_int) {
+            assert_eq!(sev.to_string(), sev.as_str());
+        }
+        assert_eq!(format!("{}", SyslogSeverity::SEV_INFO), "info");
+    }
+
+    #[test]
+    fn test_from_str() {
+        for sev in (0..=7).filter_map(SyslogSeverity::from_int) {
+            assert_eq!(SyslogSeverity::from_str(sev.as_str()), Ok(sev));
+        }
+        assert_eq!("WARNING".parse(), Ok(SyslogSeverity::SEV_WARNING));
+        assert_eq!(
+            "warn".parse::<SyslogSeverity>(),
+            Err(SyslogSeverityError::InvalidName)
+        );
+    }
+
+    #[test]
+    fn test_is_at_least() {
+        assert!(SyslogSeverity::SEV_CRIT.is_at_least(SyslogSeverity::SEV_WARNING));
+        assert!(SyslogSeverity::SEV_WARNING.is_at_least(SyslogSeverity::SEV_WARNING));
+        assert!(!SyslogSeverity::SEV_DEBUG.is_at_least(SyslogSeverity::SEV_WARNING));
+        assert!(SyslogSeverity::SEV_EMERG.is_at_least(SyslogSeverity::SEV_DEBUG));
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_from_log_level() {
+        use super::severity_from_log_level;
+
+        assert_eq!(
+            severity_from_log_level(log::Level::Error),
+            SyslogSeverity::SEV_ERR
+        );
+        assert_eq!(
+            severity_from_log_level(log::Level::Warn),
+            SyslogSeverity::SEV_WARNING
+        );
+        assert_eq!(
+            severity_from_log_level(log::Level::Info),
+            SyslogSeverity::SEV_INFO
+        );
+        assert_eq!(
+            severity_from_log_level(log::Level::Debug),
+            SyslogSeverity::SEV_DEBUG
+        );
+        assert_eq!(
+            severity_from_log_level(log::Level::Trace),
+            SyslogSeverity::SEV_DEBUG
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_from_tracing_level() {
+        use super::severity_from_tracing_level;
+
+        assert_eq!(
+            severity_from_tracing_level(tracing::Level::ERROR),
+            SyslogSeverity::SEV_ERR
+        );
+        assert_eq!(
+            severity_from_tracing_level(tracing::Level::WARN),
+            SyslogSeverity::SEV_WARNING
+        );
+        assert_eq!(
+            severity_from_tracing_level(tracing::Level::INFO),
+            SyslogSeverity::SEV_INFO
+        );
+        assert_eq!(
+            severity_from_tracing_level(tracing::Level::DEBUG),
+            SyslogSeverity::SEV_DEBUG
+        );
+        assert_eq!(
+            severity_from_tracing_level(tracing::Level::TRACE),
+            SyslogSeverity::SEV_DEBUG
+        );
+    }
 }
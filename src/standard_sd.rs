@@ -0,0 +1,221 @@
+//! Typed access to the standard SD-IDs registered by RFC 5424 section 7.
+
+use alloc::string::String;
+use core::convert::TryFrom;
+use core::str::FromStr;
+use core::time::Duration;
+
+use crate::message::StructuredData;
+
+/// The `timeQuality` SD-ID (RFC 5424 section 7.1)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TimeQuality {
+    /// `tzKnown`: whether the sender knows its time zone
+    pub tz_known: Option<bool>,
+    /// `isSynced`: whether the sender's clock is synchronized to a reliable external source
+    pub is_synced: Option<bool>,
+    /// `syncAccuracy`: how far off the sender believes its clock may be. On the wire this is an
+    /// integer number of microseconds.
+    pub sync_accuracy: Option<Duration>,
+}
+
+/// The `meta` SD-ID (RFC 5424 section 7.3)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Meta {
+    /// `sequenceId`: a counter of messages sent by the originator
+    pub sequence_id: Option<u32>,
+    /// `sysUpTime`: how long the sender has been up. On the wire this is an integer number of
+    /// hundredths of a second, as for the SNMP object of the same name.
+    pub sys_up_time: Option<Duration>,
+    /// `language`: the language of the MSG, as a BCP 47 tag. Values which don't pass
+    /// `is_valid_language_tag` are `None`.
+    pub language: Option<String>,
+}
+
+fn parse_flag(value: &str) -> Option<bool> {
+    match value {
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => None,
+    }
+}
+
+fn parse_u64(value: &str) -> Option<u64> {
+    // reject the signs and whitespace that `FromStr` would otherwise tolerate
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    u64::from_str(value).ok()
+}
+
+/// Whether `tag` is a well-formed BCP 47 language tag, at least in outline
+///
+/// This checks the basic shape (a 2- or 3-letter primary language subtag, then any number of
+/// `-`-separated subtags of 1 to 8 letters or digits, such as `en-US` or `zh-Hant-TW`) but not
+/// the registry, so well-formed nonsense like `xx-YY` passes.
+///
+/// # Example
+///
+/// ```
+/// use syslog_rfc5424::standard_sd::is_valid_language_tag;
+///
+/// assert!(is_valid_language_tag("en-US"));
+/// assert!(!is_valid_language_tag("english"));
+/// ```
+pub fn is_valid_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    (2..=3).contains(&primary.len())
+        && primary.bytes().all(|b| b.is_ascii_alphabetic())
+        && subtags
+            .all(|s| (1..=8).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_alphanumeric()))
+}
+
+impl StructuredData {
+    /// The `timeQuality` element, if present
+    ///
+    /// Params which are missing or don't have a valid value for their type are `None`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use syslog_rfc5424::parse_message;
+    ///
+    /// let msg = parse_message(
+    ///     r#"<165>1 - - - - - [timeQuality tzKnown="1" isSynced="1" syncAccuracy="60000"] -"#,
+    /// ).unwrap();
+    /// let quality = msg.sd.time_quality().unwrap();
+    ///
+    /// assert_eq!(quality.sync_accuracy, Some(Duration::from_millis(60)));
+    /// ```
+    pub fn time_quality(&self) -> Option<TimeQuality> {
+        let element = self.find_sdid("timeQuality")?;
+        let get = |name: &str| element.get(name).map(String::as_str);
+        Some(TimeQuality {
+            tz_known: get("tzKnown").and_then(parse_flag),
+            is_synced: get("isSynced").and_then(parse_flag),
+            sync_accuracy: get("syncAccuracy")
+                .and_then(parse_u64)
+                .map(Duration::from_micros),
+        })
+    }
+
+    /// The `meta` element, if present
+    ///
+    /// Params which are missing or don't have a valid value for their type are `None`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use syslog_rfc5424::parse_message;
+    ///
+    /// let msg = parse_message(
+    ///     r#"<165>1 - - - - - [meta sequenceId="29" sysUpTime="12345"] -"#,
+    /// ).unwrap();
+    /// let meta = msg.sd.meta().unwrap();
+    ///
+    /// assert_eq!(meta.sequence_id, Some(29));
+    /// assert_eq!(meta.sys_up_time, Some(Duration::from_millis(123_450)));
+    /// ```
+    pub fn meta(&self) -> Option<Meta> {
+        let element = self.find_sdid("meta")?;
+        let get = |name: &str| element.get(name).map(String::as_str);
+        Some(Meta {
+            sequence_id: get("sequenceId")
+                .and_then(parse_u64)
+                .and_then(|n| u32::try_from(n).ok())
+                .filter(|n| (1..=2_147_483_647).contains(n)),
+            sys_up_time: get("sysUpTime")
+                .and_then(parse_u64)
+                .map(|centis| Duration::from_millis(centis.saturating_mul(10))),
+            language: get("language")
+                .filter(|tag| is_valid_language_tag(tag))
+                .map(String::from),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{is_valid_language_tag, Meta, TimeQuality};
+    use crate::parser::parse_message;
+
+    #[test]
+    fn test_juniper_time_quality() {
+        let msg = parse_message(
+            r#"<165>1 2023-03-01T12:00:00.000Z mx480 mgd 3046 UI_CMDLINE_READ_LINE [junos@2636.1.1.1.2.57 pid="3046"][timeQuality tzKnown="1" isSynced="0" syncAccuracy="1500"] User 'root'"#,
+        )
+        .unwrap();
+        assert_eq!(
+            msg.sd.time_quality(),
+            Some(TimeQuality {
+                tz_known: Some(true),
+                is_synced: Some(false),
+                sync_accuracy: Some(Duration::from_micros(1500)),
+            })
+        );
+        assert_eq!(msg.sd.meta(), None);
+    }
+
+    #[test]
+    fn test_cisco_meta() {
+        // 14 days of uptime
+        let msg = parse_message(
+            r#"<189>1 2023-03-01T12:00:00Z rtr1 - - - [meta sequenceId="4711" sysUpTime="120960000" language="en-US"] %SYS-5-CONFIG_I: Configured from console"#,
+        )
+        .unwrap();
+        assert_eq!(
+            msg.sd.meta(),
+            Some(Meta {
+                sequence_id: Some(4711),
+                sys_up_time: Some(Duration::from_secs(14 * 86400)),
+                language: Some(String::from("en-US")),
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let msg = parse_message(
+            r#"<1>1 - - - - - [meta sequenceId="0" sysUpTime="-5"][timeQuality tzKnown="yes" syncAccuracy="+3"] -"#,
+        )
+        .unwrap();
+        assert_eq!(msg.sd.meta(), Some(Meta::default()));
+        assert_eq!(msg.sd.time_quality(), Some(TimeQuality::default()));
+    }
+
+    #[test]
+    fn test_language_tags() {
+        for tag in &[
+            "en",
+            "en-US",
+            "haw",
+            "zh-Hant-TW",
+            "es-419",
+            "de-CH-1996",
+            "en-us",
+        ] {
+            assert!(is_valid_language_tag(tag), "{}", tag);
+        }
+        for tag in &[
+            "",
+            "!!!",
+            "e",
+            "english",
+            "en-",
+            "-US",
+            "en--US",
+            "en_US",
+            "en-toolongtag",
+        ] {
+            assert!(!is_valid_language_tag(tag), "{}", tag);
+        }
+
+        let msg = parse_message(r#"<1>1 - - - - - [meta language="!!!"] -"#).unwrap();
+        assert_eq!(msg.sd.meta().unwrap().language, None);
+    }
+}
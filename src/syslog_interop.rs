@@ -0,0 +1,93 @@
+//! Helpers for re-sending parsed messages with the [`syslog`](https://docs.rs/syslog) crate.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::message::{ProcId, SyslogMessage};
+
+/// The parts of a `SyslogMessage` which the `syslog` crate's `Formatter5424` takes
+#[derive(Clone, Debug)]
+pub struct SyslogFormatterArgs {
+    /// For `Formatter5424::pid`; this is `0` unless the PROCID was a non-negative integer
+    pub pid: u32,
+    /// The `(message_id, structured_data, message)` tuple that `Formatter5424` formats, with
+    /// the structured data in the form of the `syslog` crate's `StructuredData` type.
+    ///
+    /// `Formatter5424` only supports numeric message ids, so the id is `0` unless the MSGID was
+    /// a non-negative integer.
+    pub message: (u32, HashMap<String, HashMap<String, String>>, String),
+}
+
+impl SyslogMessage {
+    /// Convert this message into the arguments needed to forward it with the `syslog` crate
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use syslog::{Formatter5424, LogFormat, Severity};
+    /// use syslog_rfc5424::SyslogMessage;
+    ///
+    /// let msg = r#"<78>1 - host1 CROND 10391 - [meta sequenceId="29"] hello"#
+    ///     .parse::<SyslogMessage>()
+    ///     .unwrap();
+    /// let args = msg.to_syslog_formatter_args();
+    /// let formatter = Formatter5424 {
+    ///     pid: args.pid,
+    ///     ..Default::default()
+    /// };
+    /// let mut out = Vec::new();
+    /// formatter.format(&mut out, Severity::LOG_INFO, args.message).unwrap();
+    /// ```
+    pub fn to_syslog_formatter_args(&self) -> SyslogFormatterArgs {
+        let pid = match self.procid {
+            Some(ProcId::PID(pid)) => u32::try_from(pid).unwrap_or(0),
+            _ => 0,
+        };
+        let message_id = self
+            .msgid
+            .as_ref()
+            .and_then(|msgid| msgid.parse().ok())
+            .unwrap_or(0);
+        let structured_data = self
+            .sd
+            .iter()
+            .map(|(sd_id, params)| {
+                let params = params
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.clone()))
+                    .collect::<HashMap<_, _>>();
+                (sd_id.to_string(), params)
+            })
+            .collect();
+        SyslogFormatterArgs {
+            pid,
+            message: (message_id, structured_data, self.msg.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SyslogMessage;
+
+    #[test]
+    fn test_to_syslog_formatter_args() {
+        let msg = r#"<78>1 - host1 CROND 10391 47 [meta sequenceId="29"] some_message"#
+            .parse::<SyslogMessage>()
+            .unwrap();
+        let args = msg.to_syslog_formatter_args();
+        assert_eq!(args.pid, 10391);
+        let (message_id, sd, message) = args.message;
+        assert_eq!(message_id, 47);
+        assert_eq!(sd["meta"]["sequenceId"], "29");
+        assert_eq!(message, "some_message");
+
+        let msg = "<78>1 - host1 CROND cron ID47 - some_message"
+            .parse::<SyslogMessage>()
+            .unwrap();
+        let args = msg.to_syslog_formatter_args();
+        assert_eq!(args.pid, 0);
+        assert_eq!(args.message.0, 0);
+        assert!(args.message.1.is_empty());
+    }
+}